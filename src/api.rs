@@ -1,252 +1,584 @@
 use std::{
     collections::HashSet,
+    future::Future,
     str::FromStr,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use taple_core::{
-    crypto::{KeyPair},
-    request::EventRequest as TapleEventRequestType,
+    crypto::KeyPair,
+    request::{
+        EventRequest as TapleEventRequestType, RequestState, TapleRequest as CoreTapleRequest,
+    },
     signature::Signature,
-    ApiError, DigestIdentifier, KeyDerivator, KeyIdentifier,
-    Api, Derivable,
+    Api, Derivable, DigestIdentifier, KeyDerivator, KeyIdentifier, SubjectData,
 };
-use tokio::runtime::Runtime;
+use tokio::{runtime::Handle, sync::broadcast};
 
 use crate::{
+    db::IteratorErrors,
     error::TapleError,
     models::{event::TapleSignedEvent, user_subject::UserSubject},
     models::{
         others::{SubjectAndProviders, ValidationProofAndSignatures},
         user_subject::create_subject,
+        verification::ChainVerificationReport,
     },
-    EventRequestType, TapleRequest, TapleSignature, TapleSignedEventRequest,
+    verification::verify_event_chain,
+    EventRequestType, TapleApprovalEntity, TapleApprovalState, TapleRequest, TapleSignature,
+    TapleSignedEventRequest,
 };
 
+// Number of events requested per page when walking a whole ledger
+const EVENTS_PAGE_SIZE: i64 = 100;
+
+// Longest time a request being awaited goes unchecked. Each notification drained by the host
+// checks it right away and restarts this interval, so the fallback check only runs while no
+// notification arrives, for instance when the host doesn't drain them.
+const REQUEST_RECHECK_INTERVAL_MS: u64 = 1000;
+
+pub trait RequestHandlerInterface: Send + Sync {
+    fn on_completed(&self, request: TapleRequest);
+    fn on_timeout(&self, request_id: String);
+    fn on_error(&self, error: String);
+}
+
 #[derive(Clone)]
 pub struct TapleAPI {
     pub api: Api,
-    pub runtime: Arc<Runtime>,
+    pub runtime: Handle,
+    keys: KeyPair,
+    notification_signal: broadcast::Sender<()>,
+    iterator_errors: IteratorErrors,
+}
+
+pub(crate) fn create_taple_api(
+    api: Api,
+    runtime: Handle,
     keys: KeyPair,
+    notification_signal: broadcast::Sender<()>,
+    iterator_errors: IteratorErrors,
+) -> TapleAPI {
+    TapleAPI {
+        api,
+        runtime,
+        keys,
+        notification_signal,
+        iterator_errors,
+    }
 }
 
-pub fn create_taple_api(api: Api, runtime: Arc<Runtime>, keys: KeyPair) -> TapleAPI {
-    TapleAPI { api, runtime, keys }
+/// Runs the future on the node runtime and awaits its result. The returned future can be
+/// polled by any executor, which lets the foreign async exports (Kotlin coroutines, Swift
+/// async) wait for the work without blocking a host thread. The objects moved into the future
+/// must hold a `Handle` rather than the `Runtime`, as dropping the last reference to the runtime
+/// from one of its worker threads panics.
+pub(crate) async fn run_on_runtime<T, F>(runtime: &Handle, future: F) -> Result<T, TapleError>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, TapleError>> + Send + 'static,
+{
+    runtime
+        .spawn(future)
+        .await
+        .map_err(|_| TapleError::InternalError)?
 }
 
-fn error_conversion(error: ApiError) -> TapleError {
-    match error {
-        ApiError::NotFound(msg) => TapleError::NotFound(msg),
-        _ => TapleError::ExecutionError(error.to_string()),
+/// Waits until the request leaves the `Processing` state. The request is checked as soon as the
+/// host drains a notification with `receive_blocking`, `handle_notifications` or
+/// `drop_notifications`. The core doesn't report request completion outside the notifications
+/// it hands to the host, so the request is also checked once `REQUEST_RECHECK_INTERVAL_MS` pass
+/// without any notification, when the signal lags or when it is closed.
+pub(crate) async fn wait_for_request_end(
+    api: &Api,
+    notification_signal: &broadcast::Sender<()>,
+    request_id: &DigestIdentifier,
+) -> Result<CoreTapleRequest, TapleError> {
+    let mut receiver = notification_signal.subscribe();
+    let mut signal_open = true;
+    loop {
+        let request = api.get_request(request_id.clone()).await.map_err(|e| {
+            TapleError::from_api_error("wait_for_request", Some(&request_id.to_str()), e)
+        })?;
+        if request.state != RequestState::Processing {
+            return Ok(request);
+        }
+        // Lagged receivers just check the request again
+        tokio::select! {
+            result = receiver.recv(), if signal_open => {
+                if let Err(broadcast::error::RecvError::Closed) = result {
+                    signal_open = false;
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_millis(REQUEST_RECHECK_INTERVAL_MS)) => {}
+        }
     }
 }
 
+/// `wait_for_request_end` bounded by `timeout_ms`
+pub(crate) async fn wait_for_request_completion(
+    api: &Api,
+    notification_signal: &broadcast::Sender<()>,
+    request_id: DigestIdentifier,
+    timeout_ms: u64,
+) -> Result<CoreTapleRequest, TapleError> {
+    let wait = wait_for_request_end(api, notification_signal, &request_id);
+    tokio::time::timeout(Duration::from_millis(timeout_ms), wait)
+        .await
+        .map_err(|_| TapleError::RequestTimeout {
+            request_id: request_id.to_str(),
+        })?
+}
+
 impl TapleAPI {
+    /// Fails if a database iteration failed since `errors_before` was read. The core ends the
+    /// iteration on failure, so the listing would be incomplete. Iterations run concurrently by
+    /// other calls are also detected, which can make a complete listing fail
+    fn check_iterator_errors(&self, operation: &str, errors_before: u64) -> Result<(), TapleError> {
+        if self.iterator_errors.count() != errors_before {
+            return Err(TapleError::DatabaseError {
+                operation: operation.to_owned(),
+                message: "database iteration failed, the result would be incomplete".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn user_subjects(&self, subjects: Vec<SubjectData>) -> Vec<Arc<UserSubject>> {
+        subjects
+            .into_iter()
+            .map(|s| {
+                Arc::new(create_subject(
+                    self.api.clone(),
+                    self.keys.clone(),
+                    self.runtime.clone(),
+                    RwLock::new(Some(s)),
+                    None,
+                    self.notification_signal.clone(),
+                ))
+            })
+            .collect()
+    }
+
     pub fn get_request(&self, request_id: String) -> Result<TapleRequest, TapleError> {
-        self.runtime.block_on(async {
-            let aux = self
+        self.runtime.block_on(self.get_request_async(request_id))
+    }
+
+    pub fn external_request(
+        &self,
+        event_request: TapleSignedEventRequest,
+    ) -> Result<String, TapleError> {
+        self.runtime
+            .block_on(self.external_request_async(event_request))
+    }
+
+    pub fn get_subjects(
+        &self,
+        namespace: String,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
+        self.runtime
+            .block_on(self.get_subjects_async(namespace, from, quantity))
+    }
+
+    pub fn get_governances(
+        &self,
+        namespace: String,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
+        self.runtime
+            .block_on(self.get_governances_async(namespace, from, quantity))
+    }
+
+    pub fn get_subjects_by_governance(
+        &self,
+        governance_id: String,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
+        self.runtime
+            .block_on(self.get_subjects_by_governance_async(governance_id, from, quantity))
+    }
+
+    pub fn get_events(
+        &self,
+        subject_id: String,
+        from: Option<i64>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<TapleSignedEvent>, TapleError> {
+        self.runtime
+            .block_on(self.get_events_async(subject_id, from, quantity))
+    }
+
+    pub fn get_event(&self, subject_id: String, sn: u64) -> Result<TapleSignedEvent, TapleError> {
+        self.runtime.block_on(self.get_event_async(subject_id, sn))
+    }
+
+    pub fn get_subject(&self, subject_id: String) -> Result<Arc<UserSubject>, TapleError> {
+        self.runtime.block_on(self.get_subject_async(subject_id))
+    }
+
+    pub fn add_preauthorize_subject(
+        &self,
+        subject_id: String,
+        providers: Vec<String>,
+    ) -> Result<(), TapleError> {
+        self.runtime
+            .block_on(self.add_preauthorize_subject_async(subject_id, providers))
+    }
+
+    pub fn get_all_allowed_subjects_and_providers(
+        &self,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<SubjectAndProviders>, TapleError> {
+        self.runtime
+            .block_on(self.get_all_allowed_subjects_and_providers_async(from, quantity))
+    }
+
+    pub fn add_keys(&self, derivator: String) -> Result<String, TapleError> {
+        self.runtime.block_on(self.add_keys_async(derivator))
+    }
+
+    pub fn get_validation_proof(
+        &self,
+        subject_id: String,
+    ) -> Result<ValidationProofAndSignatures, TapleError> {
+        self.runtime
+            .block_on(self.get_validation_proof_async(subject_id))
+    }
+
+    pub fn sign_event_request(
+        &self,
+        event_request: EventRequestType,
+    ) -> Result<TapleSignature, TapleError> {
+        let event_signature =
+            Signature::new::<TapleEventRequestType>(&event_request.try_into()?, &self.keys)
+                .map_err(|e| TapleError::SignatureGenerationFailed {
+                    message: e.to_string(),
+                })?;
+        Ok(event_signature.into())
+    }
+
+    pub fn get_pending_requests(&self) -> Result<Vec<TapleApprovalEntity>, TapleError> {
+        self.runtime.block_on(self.get_pending_requests_async())
+    }
+
+    pub fn get_approval(&self, id: String) -> Result<TapleApprovalEntity, TapleError> {
+        self.runtime.block_on(self.get_approval_async(id))
+    }
+
+    pub fn get_approvals(
+        &self,
+        state: Option<TapleApprovalState>,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<TapleApprovalEntity>, TapleError> {
+        self.runtime
+            .block_on(self.get_approvals_async(state, from, quantity))
+    }
+
+    pub fn approval_request(
+        &self,
+        id: String,
+        accept: bool,
+    ) -> Result<TapleApprovalEntity, TapleError> {
+        self.runtime
+            .block_on(self.approval_request_async(id, accept))
+    }
+
+    /// Returns the request once it leaves the `Processing` state, or `RequestTimeout`. It is
+    /// checked whenever the host drains a notification, and at least every second otherwise
+    pub fn wait_for_request(
+        &self,
+        request_id: String,
+        timeout_ms: u64,
+    ) -> Result<TapleRequest, TapleError> {
+        self.runtime
+            .block_on(self.wait_for_request_async(request_id, timeout_ms))
+    }
+
+    pub fn verify_subject_chain(
+        &self,
+        subject_id: String,
+    ) -> Result<ChainVerificationReport, TapleError> {
+        self.runtime
+            .block_on(self.verify_subject_chain_async(subject_id))
+    }
+
+    /// Same as `wait_for_request`, reporting the result to `handler` from the node runtime
+    pub fn wait_for_request_with_handler(
+        &self,
+        request_id: String,
+        timeout_ms: u64,
+        handler: Box<dyn RequestHandlerInterface>,
+    ) -> Result<(), TapleError> {
+        let request_id = DigestIdentifier::from_str(&request_id).map_err(|_| {
+            TapleError::DigestIdentifierGenerationFailed {
+                id: request_id.to_owned(),
+            }
+        })?;
+        let api = self.api.clone();
+        let notification_signal = self.notification_signal.clone();
+        self.runtime.spawn(async move {
+            match wait_for_request_completion(&api, &notification_signal, request_id, timeout_ms)
+                .await
+            {
+                Ok(request) => handler.on_completed(TapleRequest::from(request)),
+                Err(TapleError::RequestTimeout { request_id }) => handler.on_timeout(request_id),
+                Err(error) => handler.on_error(error.to_string()),
+            }
+        });
+        Ok(())
+    }
+}
+
+// Async exports. Each call is executed on the node runtime; the blocking methods above are
+// thin wrappers around them for hosts that can't use coroutines.
+#[uniffi::export(async_runtime = "tokio")]
+impl TapleAPI {
+    pub async fn get_request_async(&self, request_id: String) -> Result<TapleRequest, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let aux = taple_api
                 .api
-                .get_request(
-                    DigestIdentifier::from_str(&request_id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
-                )
+                .get_request(DigestIdentifier::from_str(&request_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: request_id.to_owned(),
+                    }
+                })?)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("get_request", Some(&request_id), e))?;
             Ok(TapleRequest::from(aux))
         })
+        .await
     }
 
-    pub fn external_request(
+    pub async fn external_request_async(
         &self,
         event_request: TapleSignedEventRequest,
     ) -> Result<String, TapleError> {
-        self.runtime.block_on(async {
-            let request_id = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let request_id = taple_api
                 .api
                 .external_request(event_request.try_into()?)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("external_request", None, e))?;
             Ok(request_id.to_str())
         })
+        .await
     }
 
-    pub fn get_subjects(
+    pub async fn get_subjects_async(
         &self,
         namespace: String,
         from: Option<String>,
         quantity: Option<i64>,
     ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
-        self.runtime.block_on(async {
-            let subjects = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let subjects = taple_api
                 .api
                 .get_subjects(namespace, from, quantity)
                 .await
-                .map_err(|e| error_conversion(e))?;
-            Ok(subjects
-                .into_iter()
-                .map(|s| {
-                    Arc::new(create_subject(
-                        self.api.clone(),
-                        self.keys.clone(),
-                        self.runtime.clone(),
-                        RwLock::new(Some(s)),
-                        None,
-                    ))
-                })
-                .collect())
+                .map_err(|e| TapleError::from_api_error("get_subjects", None, e))?;
+            taple_api.check_iterator_errors("get_subjects", errors_before)?;
+            Ok(taple_api.user_subjects(subjects))
         })
+        .await
     }
 
-    pub fn get_governances(
+    pub async fn get_governances_async(
         &self,
         namespace: String,
         from: Option<String>,
         quantity: Option<i64>,
     ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
-        self.runtime.block_on(async {
-            // let quantity = quantity.map(|v| v as usize);
-            let subjects = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let subjects = taple_api
                 .api
                 .get_governances(namespace, from, quantity)
                 .await
-                .map_err(|e| error_conversion(e))?;
-            Ok(subjects
-                .into_iter()
-                .map(|s| {
-                    Arc::new(create_subject(
-                        self.api.clone(),
-                        self.keys.clone(),
-                        self.runtime.clone(),
-                        RwLock::new(Some(s)),
-                        None,
-                    ))
-                })
-                .collect())
+                .map_err(|e| TapleError::from_api_error("get_governances", None, e))?;
+            taple_api.check_iterator_errors("get_governances", errors_before)?;
+            Ok(taple_api.user_subjects(subjects))
         })
+        .await
     }
 
-    pub fn get_subjects_by_governance(
+    pub async fn get_subjects_by_governance_async(
         &self,
         governance_id: String,
         from: Option<String>,
         quantity: Option<i64>,
     ) -> Result<Vec<Arc<UserSubject>>, TapleError> {
-        self.runtime.block_on(async {
-            let subjects = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let subjects = taple_api
                 .api
                 .get_subjects_by_governance(
-                    DigestIdentifier::from_str(&governance_id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                    DigestIdentifier::from_str(&governance_id).map_err(|_| {
+                        TapleError::DigestIdentifierGenerationFailed {
+                            id: governance_id.to_owned(),
+                        }
+                    })?,
                     from,
                     quantity,
                 )
                 .await
-                .map_err(|e| error_conversion(e))?;
-            Ok(subjects
-                .into_iter()
-                .map(|s| {
-                    Arc::new(create_subject(
-                        self.api.clone(),
-                        self.keys.clone(),
-                        self.runtime.clone(),
-                        RwLock::new(Some(s)),
-                        None,
-                    ))
-                })
-                .collect())
+                .map_err(|e| {
+                    TapleError::from_api_error(
+                        "get_subjects_by_governance",
+                        Some(&governance_id),
+                        e,
+                    )
+                })?;
+            taple_api.check_iterator_errors("get_subjects_by_governance", errors_before)?;
+            Ok(taple_api.user_subjects(subjects))
         })
+        .await
     }
 
-    pub fn get_events(
+    pub async fn get_events_async(
         &self,
         subject_id: String,
         from: Option<i64>,
         quantity: Option<i64>,
     ) -> Result<Vec<TapleSignedEvent>, TapleError> {
-        self.runtime.block_on(async {
-            let events = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let events = taple_api
                 .api
                 .get_events(
-                    DigestIdentifier::from_str(&subject_id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                    DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                        TapleError::DigestIdentifierGenerationFailed {
+                            id: subject_id.to_owned(),
+                        }
+                    })?,
                     from,
                     quantity,
                 )
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("get_events", Some(&subject_id), e))?;
+            taple_api.check_iterator_errors("get_events", errors_before)?;
             Ok(events.into_iter().map(|e| e.into()).collect())
         })
+        .await
     }
 
-    pub fn get_event(&self, subject_id: String, sn: u64) -> Result<TapleSignedEvent, TapleError> {
-        self.runtime.block_on(async {
-            let event = self
+    pub async fn get_event_async(
+        &self,
+        subject_id: String,
+        sn: u64,
+    ) -> Result<TapleSignedEvent, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let event = taple_api
                 .api
                 .get_event(
-                    DigestIdentifier::from_str(&subject_id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                    DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                        TapleError::DigestIdentifierGenerationFailed {
+                            id: subject_id.to_owned(),
+                        }
+                    })?,
                     sn,
                 )
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("get_event", Some(&subject_id), e))?;
             Ok(event.into())
         })
+        .await
     }
 
-    pub fn get_subject(&self, subject_id: String) -> Result<Arc<UserSubject>, TapleError> {
-        self.runtime.block_on(async {
-            let subject = self
+    pub async fn get_subject_async(
+        &self,
+        subject_id: String,
+    ) -> Result<Arc<UserSubject>, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let subject = taple_api
                 .api
-                .get_subject(
-                    DigestIdentifier::from_str(&subject_id)
-                        .map_err(|_e| TapleError::DigestIdentifierGenerationFailed)?,
-                )
+                .get_subject(DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: subject_id.to_owned(),
+                    }
+                })?)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("get_subject", Some(&subject_id), e))?;
             Ok(Arc::new(create_subject(
-                self.api.clone(),
-                self.keys.clone(),
-                self.runtime.clone(),
+                taple_api.api.clone(),
+                taple_api.keys.clone(),
+                taple_api.runtime.clone(),
                 RwLock::new(Some(subject)),
                 None,
+                taple_api.notification_signal.clone(),
             )))
         })
+        .await
     }
 
-    pub fn add_preauthorize_subject(
+    pub async fn add_preauthorize_subject_async(
         &self,
         subject_id: String,
         providers: Vec<String>,
     ) -> Result<(), TapleError> {
-        self.runtime.block_on(async {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
             let mut converted_providers = HashSet::new();
             for provider in providers {
-                converted_providers.insert(
-                    KeyIdentifier::from_str(&provider)
-                        .map_err(|_| TapleError::KeyIdentifierGenerationFailed)?,
-                );
+                converted_providers.insert(KeyIdentifier::from_str(&provider).map_err(|_| {
+                    TapleError::KeyIdentifierGenerationFailed {
+                        id: provider.to_owned(),
+                    }
+                })?);
             }
-            let subject_id = DigestIdentifier::from_str(&subject_id)
-                .map_err(|_e| TapleError::DigestIdentifierGenerationFailed)?;
-            self.api
+            let subject_id = DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: subject_id.to_owned(),
+                }
+            })?;
+            taple_api
+                .api
                 .add_preauthorize_subject(&subject_id, &converted_providers)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| {
+                    TapleError::from_api_error(
+                        "add_preauthorize_subject",
+                        Some(&subject_id.to_str()),
+                        e,
+                    )
+                })?;
             Ok(())
         })
+        .await
     }
 
-    pub fn get_all_allowed_subjects_and_providers(
+    pub async fn get_all_allowed_subjects_and_providers_async(
         &self,
         from: Option<String>,
         quantity: Option<i64>,
     ) -> Result<Vec<SubjectAndProviders>, TapleError> {
-        self.runtime.block_on(async {
-            let result = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let result = taple_api
                 .api
                 .get_all_allowed_subjects_and_providers(from, quantity)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| {
+                    TapleError::from_api_error("get_all_allowed_subjects_and_providers", None, e)
+                })?;
+            taple_api
+                .check_iterator_errors("get_all_allowed_subjects_and_providers", errors_before)?;
             Ok(result
                 .into_iter()
                 .map(|(subject_id, providers)| SubjectAndProviders {
@@ -255,49 +587,184 @@ impl TapleAPI {
                 })
                 .collect())
         })
+        .await
     }
 
-    pub fn add_keys(&self, derivator: String) -> Result<String, TapleError> {
-        let kd = KeyDerivator::from_str(&derivator).map_err(|_| TapleError::InvalidKeyDerivator)?;
+    pub async fn add_keys_async(&self, derivator: String) -> Result<String, TapleError> {
+        let kd = KeyDerivator::from_str(&derivator)
+            .map_err(|_| TapleError::InvalidKeyDerivator { derivator })?;
 
-        self.runtime.block_on(async {
-            let ki = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let ki = taple_api
                 .api
                 .add_keys(kd)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| TapleError::from_api_error("add_keys", None, e))?;
             Ok(ki.to_str())
         })
+        .await
     }
 
-    pub fn get_validation_proof(
+    pub async fn get_validation_proof_async(
         &self,
         subject_id: String,
     ) -> Result<ValidationProofAndSignatures, TapleError> {
-        self.runtime.block_on(async {
-            let (signatures, proof) = self
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let (signatures, proof) = taple_api
                 .api
-                .get_validation_proof(
-                    DigestIdentifier::from_str(&subject_id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
-                )
+                .get_validation_proof(DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: subject_id.to_owned(),
+                    }
+                })?)
                 .await
-                .map_err(|e| error_conversion(e))?;
+                .map_err(|e| {
+                    TapleError::from_api_error("get_validation_proof", Some(&subject_id), e)
+                })?;
             let signatures = signatures.into_iter().map(|s| s.into()).collect();
             Ok(ValidationProofAndSignatures {
                 signatures,
                 validation_proof: proof.into(),
             })
         })
+        .await
     }
 
-    pub fn sign_event_request(
+    pub async fn get_pending_requests_async(&self) -> Result<Vec<TapleApprovalEntity>, TapleError> {
+        self.get_approvals_async(Some(TapleApprovalState::Pending), None, None)
+            .await
+    }
+
+    pub async fn get_approval_async(&self, id: String) -> Result<TapleApprovalEntity, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let approval = taple_api
+                .api
+                .get_approval(DigestIdentifier::from_str(&id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed { id: id.to_owned() }
+                })?)
+                .await
+                .map_err(|e| TapleError::from_api_error("get_approval", Some(&id), e))?;
+            Ok(approval.into())
+        })
+        .await
+    }
+
+    pub async fn get_approvals_async(
         &self,
-        event_request: EventRequestType,
-    ) -> Result<TapleSignature, TapleError> {
-        let event_signature =
-            Signature::new::<TapleEventRequestType>(&event_request.try_into()?, &self.keys)
-                .map_err(|e| TapleError::SignatureGenerationFailed(e.to_string()))?;
-        Ok(event_signature.into())
+        state: Option<TapleApprovalState>,
+        from: Option<String>,
+        quantity: Option<i64>,
+    ) -> Result<Vec<TapleApprovalEntity>, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let errors_before = taple_api.iterator_errors.count();
+            let approvals = taple_api
+                .api
+                .get_approvals(state.map(|s| s.into()), from, quantity)
+                .await
+                .map_err(|e| TapleError::from_api_error("get_approvals", None, e))?;
+            taple_api.check_iterator_errors("get_approvals", errors_before)?;
+            Ok(approvals.into_iter().map(|a| a.into()).collect())
+        })
+        .await
+    }
+
+    pub async fn approval_request_async(
+        &self,
+        id: String,
+        accept: bool,
+    ) -> Result<TapleApprovalEntity, TapleError> {
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let approval = taple_api
+                .api
+                .approval_request(
+                    DigestIdentifier::from_str(&id).map_err(|_| {
+                        TapleError::DigestIdentifierGenerationFailed { id: id.to_owned() }
+                    })?,
+                    accept,
+                )
+                .await
+                .map_err(|e| TapleError::from_api_error("approval_request", Some(&id), e))?;
+            Ok(approval.into())
+        })
+        .await
+    }
+
+    pub async fn wait_for_request_async(
+        &self,
+        request_id: String,
+        timeout_ms: u64,
+    ) -> Result<TapleRequest, TapleError> {
+        let request_id = DigestIdentifier::from_str(&request_id).map_err(|_| {
+            TapleError::DigestIdentifierGenerationFailed {
+                id: request_id.to_owned(),
+            }
+        })?;
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let request = wait_for_request_completion(
+                &taple_api.api,
+                &taple_api.notification_signal,
+                request_id,
+                timeout_ms,
+            )
+            .await?;
+            Ok(TapleRequest::from(request))
+        })
+        .await
+    }
+
+    /// Downloads the whole ledger of the subject and verifies it locally. See
+    /// [`verify_event_chain`] for the checks performed.
+    pub async fn verify_subject_chain_async(
+        &self,
+        subject_id: String,
+    ) -> Result<ChainVerificationReport, TapleError> {
+        let subject_digest = DigestIdentifier::from_str(&subject_id).map_err(|_| {
+            TapleError::DigestIdentifierGenerationFailed {
+                id: subject_id.to_owned(),
+            }
+        })?;
+        let taple_api = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let subject = taple_api
+                .api
+                .get_subject(subject_digest.clone())
+                .await
+                .map_err(|e| {
+                    TapleError::from_api_error("verify_subject_chain", Some(&subject_id), e)
+                })?;
+            let errors_before = taple_api.iterator_errors.count();
+            let mut events = Vec::new();
+            loop {
+                let page = taple_api
+                    .api
+                    .get_events(
+                        subject_digest.clone(),
+                        Some(events.len() as i64),
+                        Some(EVENTS_PAGE_SIZE),
+                    )
+                    .await
+                    .map_err(|e| {
+                        TapleError::from_api_error("verify_subject_chain", Some(&subject_id), e)
+                    })?;
+                let page_len = page.len() as i64;
+                events.extend(page);
+                if page_len < EVENTS_PAGE_SIZE {
+                    break;
+                }
+            }
+            taple_api.check_iterator_errors("verify_subject_chain", errors_before)?;
+            Ok(verify_event_chain(
+                &subject_digest,
+                &events,
+                Some(subject.sn),
+            ))
+        })
+        .await
     }
 }
@@ -0,0 +1,525 @@
+use argon2::Params;
+use borsh::{BorshDeserialize, BorshSerialize};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use sha2::{Digest, Sha256};
+use taple_core::DatabaseManager;
+
+use crate::{
+    db::{self, read_all, NodeDatabase, WrapperManager, WriteOp},
+    error::BackupError,
+    keystore::{passphrase_key, stored_params},
+    DatabaseManagerInterface, DbError,
+};
+
+const BACKUP_MAGIC: &[u8; 8] = b"TAPLEBAK";
+const BACKUP_VERSION: u8 = 1;
+// Magic and version, authenticated as associated data when the archive is encrypted
+const HEADER_LEN: usize = 9;
+const SALT_LEN: usize = 16;
+// Entries written per batch when restoring
+const RESTORE_BATCH_SIZE: usize = 256;
+
+/// Archive layout after the header. The checksum covers the payload as stored, so corruption
+/// is detected before decrypting it.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct ArchiveBody {
+    encryption: Option<ArchiveEncryption>,
+    checksum: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+/// Payload encrypted with ChaCha20-Poly1305 under a key derived from the passphrase with
+/// Argon2id
+#[derive(BorshSerialize, BorshDeserialize)]
+struct ArchiveEncryption {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct BackupContents {
+    collections: Vec<BackupCollection>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct BackupCollection {
+    identifier: String,
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+fn db_error(error: DbError) -> BackupError {
+    BackupError::DatabaseError(error.to_string())
+}
+
+fn header() -> Vec<u8> {
+    let mut header = BACKUP_MAGIC.to_vec();
+    header.push(BACKUP_VERSION);
+    header
+}
+
+fn encrypt(payload: &[u8], passphrase: &str) -> Result<(ArchiveEncryption, Vec<u8>), BackupError> {
+    let params = Params::default();
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let key =
+        passphrase_key(passphrase, &salt, params.clone()).map_err(|_| BackupError::CryptoError)?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: payload,
+                aad: &header(),
+            },
+        )
+        .map_err(|_| BackupError::CryptoError)?;
+    let encryption = ArchiveEncryption {
+        m_cost: params.m_cost(),
+        t_cost: params.t_cost(),
+        p_cost: params.p_cost(),
+        salt: salt.to_vec(),
+        nonce: nonce.to_vec(),
+    };
+    Ok((encryption, ciphertext))
+}
+
+fn decrypt(
+    encryption: &ArchiveEncryption,
+    payload: &[u8],
+    passphrase: &str,
+) -> Result<Vec<u8>, BackupError> {
+    if encryption.nonce.len() != 12 {
+        return Err(BackupError::InvalidArchive);
+    }
+    let params = stored_params(encryption.m_cost, encryption.t_cost, encryption.p_cost)
+        .ok_or(BackupError::InvalidArchive)?;
+    let key = passphrase_key(passphrase, &encryption.salt, params)
+        .map_err(|_| BackupError::CryptoError)?;
+    // The checksum already matched, so a failure means a wrong passphrase
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(&encryption.nonce),
+            Payload {
+                msg: payload,
+                aad: &header(),
+            },
+        )
+        .map_err(|_| BackupError::WrongPassphrase)
+}
+
+fn open_archive(archive: &[u8], passphrase: Option<&str>) -> Result<BackupContents, BackupError> {
+    if archive.len() < HEADER_LEN || &archive[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+        return Err(BackupError::InvalidArchive);
+    }
+    if archive[BACKUP_MAGIC.len()] != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion);
+    }
+    let body = ArchiveBody::try_from_slice(&archive[HEADER_LEN..])
+        .map_err(|_| BackupError::InvalidArchive)?;
+    if Sha256::digest(&body.payload).as_slice() != body.checksum.as_slice() {
+        return Err(BackupError::ChecksumMismatch);
+    }
+    let payload = match (&body.encryption, passphrase) {
+        (Some(encryption), Some(passphrase)) => decrypt(encryption, &body.payload, passphrase)?,
+        (Some(_), None) => return Err(BackupError::PassphraseRequired),
+        (None, _) => body.payload,
+    };
+    BackupContents::try_from_slice(&payload).map_err(|_| BackupError::InvalidArchive)
+}
+
+/// Archives every collection opened by the core. The entries are read decrypted, so the archive
+/// doesn't depend on the `database_key` of the node
+pub(crate) fn export_backup(
+    database: &NodeDatabase,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, BackupError> {
+    let identifiers: Vec<String> = database
+        .collections
+        .lock()
+        .map_err(|_| BackupError::InternalError)?
+        .iter()
+        .cloned()
+        .collect();
+    let mut collections = Vec::with_capacity(identifiers.len());
+    for identifier in identifiers {
+        let iterator = database
+            .manager
+            .create_collection(identifier.clone())
+            .iter(false, String::new())
+            .map_err(db_error)?;
+        let entries = read_all(iterator.as_ref())
+            .map_err(db_error)?
+            .into_iter()
+            .map(|tuple| (tuple.key, tuple.value))
+            .collect();
+        collections.push(BackupCollection {
+            identifier,
+            entries,
+        });
+    }
+    let payload = BackupContents { collections }
+        .try_to_vec()
+        .map_err(|_| BackupError::InternalError)?;
+    let (encryption, payload) = match passphrase {
+        Some(passphrase) => {
+            let (encryption, ciphertext) = encrypt(&payload, passphrase)?;
+            (Some(encryption), ciphertext)
+        }
+        None => (None, payload),
+    };
+    let body = ArchiveBody {
+        encryption,
+        checksum: Sha256::digest(&payload).to_vec(),
+        payload,
+    };
+    let mut archive = header();
+    archive.extend(body.try_to_vec().map_err(|_| BackupError::InternalError)?);
+    Ok(archive)
+}
+
+/// Applies `ops` to the collection in batches
+fn write_ops(
+    manager: &WrapperManager,
+    identifier: &str,
+    ops: impl Iterator<Item = WriteOp>,
+) -> Result<(), BackupError> {
+    let target = manager.create_collection(identifier);
+    let mut ops = ops.peekable();
+    while ops.peek().is_some() {
+        target
+            .write_batch(ops.by_ref().take(RESTORE_BATCH_SIZE).collect())
+            .map_err(|e| BackupError::DatabaseError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Deletes every entry of the collection
+fn clear_collection(manager: &WrapperManager, identifier: &str) -> Result<(), BackupError> {
+    let iterator = manager
+        .inner_manager
+        .create_collection(identifier.to_owned())
+        .iter(false, String::new())
+        .map_err(db_error)?;
+    let keys = read_all(iterator.as_ref()).map_err(db_error)?;
+    write_ops(
+        manager,
+        identifier,
+        keys.into_iter()
+            .map(|tuple| WriteOp::Del { key: tuple.key }),
+    )
+}
+
+/// Validates a backup and writes it to an empty database, which can then be passed to `start`
+/// with the same `database_key` and `hash_database_keys` settings. The keystore isn't part of
+/// the backup; move the node key with `keystore_export`. Only a host database manager can be
+/// restored into, not the native in-memory or SQLite ones.
+///
+/// The collections of the archive must be empty unless `overwrite` is set, in which case their
+/// entries are deleted before writing. If writing fails, the entries of the archive are deleted
+/// again so the database isn't left half restored; the entries removed by `overwrite` are lost.
+/// The rollback is best effort, and a database left with part of the backup can always be
+/// restored again with `overwrite`.
+pub fn restore_backup(
+    manager: Box<dyn DatabaseManagerInterface>,
+    archive: Vec<u8>,
+    passphrase: Option<String>,
+    database_key: Option<Vec<u8>>,
+    hash_database_keys: Option<bool>,
+    overwrite: Option<bool>,
+) -> Result<(), BackupError> {
+    let contents = open_archive(&archive, passphrase.as_deref())?;
+    let manager = db::with_encryption(
+        manager,
+        database_key.as_deref(),
+        hash_database_keys.unwrap_or(false),
+    )
+    .map_err(|e| BackupError::InvalidSettings(e.to_string()))?;
+    let wrapper_manager = NodeDatabase::new(manager, 0).wrapper_manager();
+    // Checked, or cleared, before writing anything so the backup is never mixed with existing data
+    for collection in contents.collections.iter() {
+        if overwrite.unwrap_or(false) {
+            clear_collection(&wrapper_manager, &collection.identifier)?;
+            continue;
+        }
+        let iterator = wrapper_manager
+            .inner_manager
+            .create_collection(collection.identifier.clone())
+            .iter(false, String::new())
+            .map_err(db_error)?;
+        if iterator.next().map_err(db_error)?.is_some() {
+            return Err(BackupError::DatabaseNotEmpty(collection.identifier.clone()));
+        }
+    }
+    let result = contents.collections.iter().try_for_each(|collection| {
+        let ops = collection.entries.iter().map(|(key, value)| WriteOp::Put {
+            key: key.clone(),
+            value: value.clone(),
+        });
+        write_ops(&wrapper_manager, &collection.identifier, ops)
+    });
+    if result.is_err() {
+        // The collections were empty, so deleting every key of the archive undoes the writes
+        for collection in contents.collections.iter() {
+            let ops = collection
+                .entries
+                .iter()
+                .map(|(key, _)| WriteOp::Del { key: key.clone() });
+            if let Err(error) = write_ops(&wrapper_manager, &collection.identifier, ops) {
+                log::error!(
+                    "Rollback of the restore of collection {} failed: {}",
+                    collection.identifier,
+                    error
+                );
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        DbCollectionInterface, DbCollectionIteratorInterface, InMemoryDatabaseManager,
+    };
+    use taple_core::DatabaseCollection;
+
+    /// Node database with two collections
+    fn node_database() -> NodeDatabase {
+        let database = NodeDatabase::new(Box::new(InMemoryDatabaseManager::new()), 0);
+        let manager = database.wrapper_manager();
+        let subjects = manager.create_collection("subjects");
+        subjects.put("a", vec![1]).unwrap();
+        subjects.put("b", vec![2]).unwrap();
+        manager
+            .create_collection("events")
+            .put("c", vec![3])
+            .unwrap();
+        database
+    }
+
+    fn entries(manager: &InMemoryDatabaseManager, identifier: &str) -> Vec<(String, Vec<u8>)> {
+        let iterator = manager
+            .create_collection(identifier.into())
+            .iter(false, String::new())
+            .unwrap();
+        read_all(iterator.as_ref())
+            .unwrap()
+            .into_iter()
+            .map(|tuple| (tuple.key, tuple.value))
+            .collect()
+    }
+
+    fn restore(
+        manager: &InMemoryDatabaseManager,
+        archive: &[u8],
+        passphrase: Option<&str>,
+    ) -> Result<(), BackupError> {
+        restore_backup(
+            Box::new(manager.clone()),
+            archive.to_vec(),
+            passphrase.map(str::to_owned),
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Database whose puts of the key `fail` fail, without batch support
+    #[derive(Debug)]
+    struct FailingManager(InMemoryDatabaseManager);
+
+    #[derive(Debug)]
+    struct FailingCollection(Box<dyn DbCollectionInterface>);
+
+    impl DatabaseManagerInterface for FailingManager {
+        fn create_collection(&self, identifier: String) -> Box<dyn DbCollectionInterface> {
+            Box::new(FailingCollection(self.0.create_collection(identifier)))
+        }
+    }
+
+    impl DbCollectionInterface for FailingCollection {
+        fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError> {
+            self.0.get(key)
+        }
+
+        fn put(&self, key: String, value: Vec<u8>) -> Result<(), DbError> {
+            match key.as_str() {
+                "fail" => Err(DbError::InternalDbError),
+                _ => self.0.put(key, value),
+            }
+        }
+
+        fn del(&self, key: String) -> Result<(), DbError> {
+            self.0.del(key)
+        }
+
+        fn iter(
+            &self,
+            reverse: bool,
+            prefix: String,
+        ) -> Result<Box<dyn DbCollectionIteratorInterface>, DbError> {
+            self.0.iter(reverse, prefix)
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let archive = export_backup(&node_database(), None).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        restore(&target, &archive, None).unwrap();
+        assert_eq!(
+            entries(&target, "subjects"),
+            vec![("a".to_owned(), vec![1]), ("b".to_owned(), vec![2])]
+        );
+        assert_eq!(entries(&target, "events"), vec![("c".to_owned(), vec![3])]);
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let archive = export_backup(&node_database(), Some("passphrase")).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        assert!(matches!(
+            restore(&target, &archive, None),
+            Err(BackupError::PassphraseRequired)
+        ));
+        assert!(matches!(
+            restore(&target, &archive, Some("wrong")),
+            Err(BackupError::WrongPassphrase)
+        ));
+        assert!(entries(&target, "subjects").is_empty());
+        restore(&target, &archive, Some("passphrase")).unwrap();
+        assert_eq!(entries(&target, "events"), vec![("c".to_owned(), vec![3])]);
+    }
+
+    #[test]
+    fn restore_with_database_key() {
+        let archive = export_backup(&node_database(), None).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        let database_key = vec![7; 32];
+        restore_backup(
+            Box::new(target.clone()),
+            archive,
+            None,
+            Some(database_key.clone()),
+            Some(true),
+            None,
+        )
+        .unwrap();
+        let manager = db::with_encryption(Box::new(target), Some(&database_key), true).unwrap();
+        assert_eq!(
+            manager
+                .create_collection("subjects".into())
+                .get("b".into())
+                .unwrap(),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn corrupted_archives() {
+        let archive = export_backup(&node_database(), None).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        // The payload is the last field of the archive
+        let mut corrupted = archive.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            restore(&target, &corrupted, None),
+            Err(BackupError::ChecksumMismatch)
+        ));
+        let mut version = archive.clone();
+        version[BACKUP_MAGIC.len()] = BACKUP_VERSION + 1;
+        assert!(matches!(
+            restore(&target, &version, None),
+            Err(BackupError::UnsupportedVersion)
+        ));
+        assert!(matches!(
+            restore(&target, b"not a backup", None),
+            Err(BackupError::InvalidArchive)
+        ));
+        assert!(matches!(
+            restore(&target, &archive[..HEADER_LEN + 4], None),
+            Err(BackupError::InvalidArchive)
+        ));
+    }
+
+    #[test]
+    fn excessive_argon2_costs_are_rejected() {
+        let payload = vec![0u8; 32];
+        let body = ArchiveBody {
+            encryption: Some(ArchiveEncryption {
+                m_cost: u32::MAX,
+                t_cost: u32::MAX,
+                p_cost: 1,
+                salt: vec![0; SALT_LEN],
+                nonce: vec![0; 12],
+            }),
+            checksum: Sha256::digest(&payload).to_vec(),
+            payload,
+        };
+        let mut archive = header();
+        archive.extend(body.try_to_vec().unwrap());
+        assert!(matches!(
+            restore(
+                &InMemoryDatabaseManager::new(),
+                &archive,
+                Some("passphrase")
+            ),
+            Err(BackupError::InvalidArchive)
+        ));
+    }
+
+    #[test]
+    fn restore_needs_empty_collections() {
+        let archive = export_backup(&node_database(), None).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        target
+            .create_collection("events".into())
+            .put("old".into(), vec![0])
+            .unwrap();
+        assert!(matches!(
+            restore(&target, &archive, None),
+            Err(BackupError::DatabaseNotEmpty(identifier)) if identifier == "events"
+        ));
+        assert!(entries(&target, "subjects").is_empty());
+        restore_backup(
+            Box::new(target.clone()),
+            archive,
+            None,
+            None,
+            None,
+            Some(true),
+        )
+        .unwrap();
+        assert_eq!(entries(&target, "events"), vec![("c".to_owned(), vec![3])]);
+    }
+
+    #[test]
+    fn failed_restore_is_rolled_back() {
+        let database = node_database();
+        database
+            .wrapper_manager()
+            .create_collection("subjects")
+            .put("fail", vec![4])
+            .unwrap();
+        let archive = export_backup(&database, None).unwrap();
+        let target = InMemoryDatabaseManager::new();
+        let result = restore_backup(
+            Box::new(FailingManager(target.clone())),
+            archive,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(matches!(result, Err(BackupError::DatabaseError(_))));
+        assert!(entries(&target, "subjects").is_empty());
+        assert!(entries(&target, "events").is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use lru::LruCache;
+
+pub struct DatabaseCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries cached across every collection
+    pub entries: u64,
+}
+
+/// Read-through caches of a node, one per collection identifier. The core opens the same
+/// collection several times, so every `WrapperCollection` of an identifier shares its cache.
+#[derive(Clone, Debug)]
+pub(crate) struct DatabaseCache {
+    capacity: Option<NonZeroUsize>,
+    collections: Arc<Mutex<HashMap<String, Arc<Mutex<CollectionCache>>>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl DatabaseCache {
+    /// Cache of up to `capacity` entries per collection. A capacity of 0 disables it
+    pub(crate) fn new(capacity: u32) -> Self {
+        Self {
+            capacity: NonZeroUsize::new(capacity as usize),
+            collections: Arc::new(Mutex::new(HashMap::new())),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn collection(&self, identifier: &str) -> Option<CollectionCacheHandle> {
+        let capacity = self.capacity?;
+        let mut collections = self.collections.lock().ok()?;
+        let cache = collections
+            .entry(identifier.to_owned())
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(CollectionCache {
+                    entries: LruCache::new(capacity),
+                    generation: 0,
+                }))
+            })
+            .clone();
+        Some(CollectionCacheHandle {
+            cache,
+            hits: self.hits.clone(),
+            misses: self.misses.clone(),
+        })
+    }
+
+    pub(crate) fn stats(&self) -> DatabaseCacheStats {
+        let entries = match self.collections.lock() {
+            Ok(collections) => collections
+                .values()
+                .filter_map(|cache| cache.lock().ok().map(|cache| cache.entries.len() as u64))
+                .sum(),
+            Err(_) => 0,
+        };
+        DatabaseCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CollectionCache {
+    entries: LruCache<String, Vec<u8>>,
+    /// Increased on every invalidation, so a value read from the host database while the
+    /// entry was being written isn't cached
+    generation: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct CollectionCacheHandle {
+    cache: Arc<Mutex<CollectionCache>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl CollectionCacheHandle {
+    /// Returns the cached value, or the generation to pass to `insert` after reading the value
+    /// from the host database
+    pub(crate) fn get(&self, key: &str) -> Result<Vec<u8>, Option<u64>> {
+        let Ok(mut cache) = self.cache.lock() else {
+            return Err(None);
+        };
+        match cache.entries.get(key) {
+            Some(value) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(value.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Err(Some(cache.generation))
+            }
+        }
+    }
+
+    pub(crate) fn insert(&self, generation: u64, key: &str, value: &[u8]) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if cache.generation == generation {
+                cache.entries.put(key.to_owned(), value.to_vec());
+            }
+        }
+    }
+
+    pub(crate) fn invalidate<'a>(&self, keys: impl IntoIterator<Item = &'a str>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            for key in keys {
+                cache.entries.pop(key);
+            }
+            cache.generation += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_without_capacity() {
+        let cache = DatabaseCache::new(0);
+        assert!(cache.collection("test").is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let cache = DatabaseCache::new(4);
+        let collection = cache.collection("test").unwrap();
+        let generation = collection.get("a").unwrap_err().unwrap();
+        collection.insert(generation, "a", &[1]);
+        assert_eq!(collection.get("a"), Ok(vec![1]));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+
+    #[test]
+    fn invalidate_removes_entries() {
+        let cache = DatabaseCache::new(4);
+        let collection = cache.collection("test").unwrap();
+        let generation = collection.get("a").unwrap_err().unwrap();
+        collection.insert(generation, "a", &[1]);
+        collection.insert(generation, "b", &[2]);
+        collection.invalidate(["a"]);
+        assert!(collection.get("a").is_err());
+        assert_eq!(collection.get("b"), Ok(vec![2]));
+    }
+
+    #[test]
+    fn stale_generation_is_not_cached() {
+        let cache = DatabaseCache::new(4);
+        let collection = cache.collection("test").unwrap();
+        // A read misses, then a write of the same key lands before the read value is cached
+        let generation = collection.get("a").unwrap_err().unwrap();
+        collection.invalidate(["a"]);
+        collection.insert(generation, "a", &[1]);
+        assert!(collection.get("a").is_err());
+        // A read started after the write is cached
+        let generation = collection.get("a").unwrap_err().unwrap();
+        collection.insert(generation, "a", &[2]);
+        assert_eq!(collection.get("a"), Ok(vec![2]));
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let cache = DatabaseCache::new(2);
+        let collection = cache.collection("test").unwrap();
+        let generation = collection.get("a").unwrap_err().unwrap();
+        collection.insert(generation, "a", &[1]);
+        collection.insert(generation, "b", &[2]);
+        assert!(collection.get("a").is_ok());
+        collection.insert(generation, "c", &[3]);
+        assert!(collection.get("b").is_err());
+        assert!(collection.get("a").is_ok());
+        assert!(collection.get("c").is_ok());
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn collections_share_their_cache() {
+        let cache = DatabaseCache::new(4);
+        let first = cache.collection("test").unwrap();
+        let second = cache.collection("test").unwrap();
+        let other = cache.collection("other").unwrap();
+        let generation = first.get("a").unwrap_err().unwrap();
+        first.insert(generation, "a", &[1]);
+        assert_eq!(second.get("a"), Ok(vec![1]));
+        assert!(other.get("a").is_err());
+        // An invalidation through one handle applies to the other
+        second.invalidate(["a"]);
+        assert!(first.get("a").is_err());
+    }
+
+    #[test]
+    fn writes_invalidate_cached_reads() {
+        use crate::db::{InMemoryDatabaseManager, NodeDatabase};
+        use taple_core::{DatabaseCollection, DatabaseManager};
+
+        let database = NodeDatabase::new(Box::new(InMemoryDatabaseManager::new()), 4);
+        let collection = database.wrapper_manager().create_collection("test");
+        collection.put("a", vec![1]).unwrap();
+        assert_eq!(collection.get("a").unwrap(), vec![1]);
+        assert_eq!(collection.get("a").unwrap(), vec![1]);
+        assert_eq!(database.cache.stats().hits, 1);
+        collection.put("a", vec![2]).unwrap();
+        assert_eq!(collection.get("a").unwrap(), vec![2]);
+        collection.del("a").unwrap();
+        assert!(collection.get("a").is_err());
+    }
+}
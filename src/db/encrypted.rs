@@ -0,0 +1,569 @@
+use std::sync::{Arc, Mutex};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::{DbError, SettingsError};
+
+use super::{
+    read_batches, DatabaseManagerInterface, DbCollectionInterface, DbCollectionIteratorInterface,
+    Tuple, WriteOp,
+};
+
+const ENTRY_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const DATABASE_KEY_LEN: usize = 32;
+// Separator placed by the core between the elements of a key
+const KEY_SEPARATOR: char = char::MAX;
+// Separator between the MACs of the key elements in the stored keys
+const MAC_SEPARATOR: char = '/';
+// Bytes of each key element MAC kept in the stored keys
+const KEY_MAC_LEN: usize = 16;
+
+/// Entry as stored in the host database, encrypted with ChaCha20-Poly1305. The key is kept
+/// inside the ciphertext so entries can't be moved to another key, and so the original keys
+/// can be recovered when the stored ones are MACs.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct EntryPlaintext {
+    key: String,
+    value: Vec<u8>,
+}
+
+struct Cipher {
+    values: ChaCha20Poly1305,
+    /// Present when the keys are stored as MACs
+    key_mac: Option<[u8; 32]>,
+}
+
+impl Cipher {
+    fn derive_key(database_key: &[u8], label: &[u8]) -> Result<[u8; 32], SettingsError> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(database_key)
+            .map_err(|_| SettingsError::InvalidDatabaseKey)?;
+        mac.update(label);
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&mac.finalize().into_bytes());
+        Ok(key)
+    }
+
+    fn new(database_key: &[u8], hash_keys: bool) -> Result<Self, SettingsError> {
+        if database_key.len() != DATABASE_KEY_LEN {
+            return Err(SettingsError::InvalidDatabaseKey);
+        }
+        let values_key = Self::derive_key(database_key, b"taple-ffi database values")?;
+        let key_mac = match hash_keys {
+            true => Some(Self::derive_key(database_key, b"taple-ffi database keys")?),
+            false => None,
+        };
+        Ok(Self {
+            values: ChaCha20Poly1305::new(Key::from_slice(&values_key)),
+            key_mac,
+        })
+    }
+
+    fn element_mac(key_mac: &[u8; 32], collection: &str, element: &str) -> String {
+        // Infallible, HMAC accepts keys of any length
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key_mac).expect("HMAC key");
+        mac.update(collection.as_bytes());
+        mac.update(&[0]);
+        mac.update(element.as_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..KEY_MAC_LEN])
+    }
+
+    /// Key the entry is stored under. Each key element is replaced by its MAC so prefixes made
+    /// of whole elements can still be looked up
+    fn stored_key(&self, collection: &str, key: &str) -> String {
+        match &self.key_mac {
+            Some(key_mac) => key
+                .split(KEY_SEPARATOR)
+                .map(|element| Self::element_mac(key_mac, collection, element))
+                .collect::<Vec<String>>()
+                .join(&MAC_SEPARATOR.to_string()),
+            None => key.to_owned(),
+        }
+    }
+
+    /// Stored prefix matching every entry whose key starts with `prefix`. When the keys are
+    /// stored as MACs the last, maybe incomplete, element of the prefix is left out, so the
+    /// results must still be filtered by their original key
+    fn stored_prefix(&self, collection: &str, prefix: &str) -> String {
+        match &self.key_mac {
+            Some(key_mac) => {
+                let mut elements: Vec<&str> = prefix.split(KEY_SEPARATOR).collect();
+                elements.pop();
+                elements
+                    .into_iter()
+                    .map(|element| {
+                        let mut mac = Self::element_mac(key_mac, collection, element);
+                        mac.push(MAC_SEPARATOR);
+                        mac
+                    })
+                    .collect()
+            }
+            None => prefix.to_owned(),
+        }
+    }
+
+    fn seal(&self, collection: &str, key: String, value: Vec<u8>) -> Result<Vec<u8>, DbError> {
+        let plaintext = EntryPlaintext { key, value }
+            .try_to_vec()
+            .map_err(|_| DbError::InternalDbError)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .values
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: collection.as_bytes(),
+                },
+            )
+            .map_err(|_| DbError::InternalDbError)?;
+        let mut sealed = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        sealed.push(ENTRY_VERSION);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts the entry stored under `stored_key`, failing if it was modified or moved
+    fn open(
+        &self,
+        collection: &str,
+        stored_key: &str,
+        sealed: &[u8],
+    ) -> Result<EntryPlaintext, DbError> {
+        let entry = self.decrypt(collection, sealed).and_then(|entry| {
+            match self.stored_key(collection, &entry.key) == stored_key {
+                true => Ok(entry),
+                false => Err(DbError::AuthenticationFailed),
+            }
+        });
+        if entry.is_err() {
+            log::error!(
+                "Entry {} of collection {} failed authentication",
+                stored_key,
+                collection
+            );
+        }
+        entry
+    }
+
+    fn decrypt(&self, collection: &str, sealed: &[u8]) -> Result<EntryPlaintext, DbError> {
+        if sealed.len() < 1 + NONCE_LEN || sealed[0] != ENTRY_VERSION {
+            return Err(DbError::AuthenticationFailed);
+        }
+        let plaintext = self
+            .values
+            .decrypt(
+                Nonce::from_slice(&sealed[1..1 + NONCE_LEN]),
+                Payload {
+                    msg: &sealed[1 + NONCE_LEN..],
+                    aad: collection.as_bytes(),
+                },
+            )
+            .map_err(|_| DbError::AuthenticationFailed)?;
+        EntryPlaintext::try_from_slice(&plaintext).map_err(|_| DbError::AuthenticationFailed)
+    }
+}
+
+/// Encrypts the values written to the host database with the `database_key` of the settings,
+/// and optionally replaces the keys with MACs. Works on top of any `DatabaseManagerInterface`.
+pub(super) struct EncryptedDatabaseManager {
+    inner_manager: Box<dyn DatabaseManagerInterface>,
+    cipher: Arc<Cipher>,
+}
+
+impl std::fmt::Debug for EncryptedDatabaseManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedDatabaseManager")
+            .field("inner_manager", &self.inner_manager)
+            .field("hash_keys", &self.cipher.key_mac.is_some())
+            .finish()
+    }
+}
+
+impl EncryptedDatabaseManager {
+    pub(super) fn new(
+        inner_manager: Box<dyn DatabaseManagerInterface>,
+        database_key: &[u8],
+        hash_keys: bool,
+    ) -> Result<Self, SettingsError> {
+        Ok(Self {
+            inner_manager,
+            cipher: Arc::new(Cipher::new(database_key, hash_keys)?),
+        })
+    }
+}
+
+impl DatabaseManagerInterface for EncryptedDatabaseManager {
+    fn create_collection(&self, identifier: String) -> Box<dyn DbCollectionInterface> {
+        Box::new(EncryptedCollection {
+            inner_collection: self.inner_manager.create_collection(identifier.clone()),
+            identifier,
+            cipher: self.cipher.clone(),
+        })
+    }
+}
+
+struct EncryptedCollection {
+    inner_collection: Box<dyn DbCollectionInterface>,
+    identifier: String,
+    cipher: Arc<Cipher>,
+}
+
+impl std::fmt::Debug for EncryptedCollection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedCollection")
+            .field("identifier", &self.identifier)
+            .finish()
+    }
+}
+
+impl EncryptedCollection {
+    fn seal_op(&self, op: WriteOp) -> Result<WriteOp, DbError> {
+        Ok(match op {
+            WriteOp::Put { key, value } => WriteOp::Put {
+                key: self.cipher.stored_key(&self.identifier, &key),
+                value: self.cipher.seal(&self.identifier, key, value)?,
+            },
+            WriteOp::Del { key } => WriteOp::Del {
+                key: self.cipher.stored_key(&self.identifier, &key),
+            },
+        })
+    }
+}
+
+impl DbCollectionInterface for EncryptedCollection {
+    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError> {
+        let stored_key = self.cipher.stored_key(&self.identifier, &key);
+        match self.inner_collection.get(stored_key.clone())? {
+            Some(sealed) => Ok(Some(
+                self.cipher
+                    .open(&self.identifier, &stored_key, &sealed)?
+                    .value,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: String, value: Vec<u8>) -> Result<(), DbError> {
+        let stored_key = self.cipher.stored_key(&self.identifier, &key);
+        let sealed = self.cipher.seal(&self.identifier, key, value)?;
+        self.inner_collection.put(stored_key, sealed)
+    }
+
+    fn del(&self, key: String) -> Result<(), DbError> {
+        self.inner_collection
+            .del(self.cipher.stored_key(&self.identifier, &key))
+    }
+
+    fn iter(
+        &self,
+        reverse: bool,
+        prefix: String,
+    ) -> Result<Box<dyn DbCollectionIteratorInterface>, DbError> {
+        let stored_prefix = self.cipher.stored_prefix(&self.identifier, &prefix);
+        if self.cipher.key_mac.is_none() {
+            return Ok(Box::new(DecryptingIterator {
+                inner_iterator: self.inner_collection.iter(reverse, stored_prefix)?,
+                collection: self.identifier.clone(),
+                cipher: self.cipher.clone(),
+            }));
+        }
+        // MACs don't keep the key order, so the matching entries are decrypted and sorted by
+        // their original key. They are decrypted a batch at a time as they are read, so only
+        // the entries matching the whole prefix are held in memory
+        let decrypting_iterator = DecryptingIterator {
+            inner_iterator: self.inner_collection.iter(false, stored_prefix)?,
+            collection: self.identifier.clone(),
+            cipher: self.cipher.clone(),
+        };
+        let mut tuples = Vec::new();
+        read_batches(&decrypting_iterator, |batch| {
+            tuples.extend(
+                batch
+                    .into_iter()
+                    .filter(|tuple| tuple.key.starts_with(&prefix)),
+            )
+        })?;
+        tuples.sort_by(|a, b| a.key.cmp(&b.key));
+        if reverse {
+            tuples.reverse();
+        }
+        Ok(Box::new(SortedIterator {
+            tuples: Mutex::new(tuples.into_iter()),
+        }))
+    }
+
+    fn write_batch(&self, ops: Vec<WriteOp>) -> Result<bool, DbError> {
+        let ops = ops
+            .into_iter()
+            .map(|op| self.seal_op(op))
+            .collect::<Result<Vec<WriteOp>, DbError>>()?;
+        self.inner_collection.write_batch(ops)
+    }
+}
+
+struct DecryptingIterator {
+    inner_iterator: Box<dyn DbCollectionIteratorInterface>,
+    collection: String,
+    cipher: Arc<Cipher>,
+}
+
+impl std::fmt::Debug for DecryptingIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecryptingIterator")
+            .field("collection", &self.collection)
+            .finish()
+    }
+}
+
+impl DecryptingIterator {
+    fn open(&self, tuple: Tuple) -> Result<Tuple, DbError> {
+        let entry = self
+            .cipher
+            .open(&self.collection, &tuple.key, &tuple.value)?;
+        Ok(Tuple {
+            key: entry.key,
+            value: entry.value,
+        })
+    }
+}
+
+impl DbCollectionIteratorInterface for DecryptingIterator {
+    fn next(&self) -> Result<Option<Tuple>, DbError> {
+        self.inner_iterator
+            .next()?
+            .map(|tuple| self.open(tuple))
+            .transpose()
+    }
+
+    fn next_batch(&self, max: u32) -> Result<Option<Vec<Tuple>>, DbError> {
+        self.inner_iterator
+            .next_batch(max)?
+            .map(|batch| batch.into_iter().map(|tuple| self.open(tuple)).collect())
+            .transpose()
+    }
+}
+
+#[derive(Debug)]
+struct SortedIterator {
+    tuples: Mutex<std::vec::IntoIter<Tuple>>,
+}
+
+impl DbCollectionIteratorInterface for SortedIterator {
+    fn next(&self) -> Result<Option<Tuple>, DbError> {
+        let mut tuples = self.tuples.lock().map_err(|_| DbError::InternalDbError)?;
+        Ok(tuples.next())
+    }
+
+    fn next_batch(&self, max: u32) -> Result<Option<Vec<Tuple>>, DbError> {
+        let mut tuples = self.tuples.lock().map_err(|_| DbError::InternalDbError)?;
+        Ok(Some(tuples.by_ref().take(max as usize).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{read_all, InMemoryDatabaseManager};
+
+    const DATABASE_KEY: [u8; 32] = [7; 32];
+
+    fn key(elements: &[&str]) -> String {
+        elements.join(&KEY_SEPARATOR.to_string())
+    }
+
+    fn keys(iterator: Box<dyn DbCollectionIteratorInterface>) -> Vec<String> {
+        let mut keys = Vec::new();
+        while let Some(tuple) = iterator.next().unwrap() {
+            keys.push(tuple.key);
+        }
+        keys
+    }
+
+    /// Encrypted manager and the host database under it
+    fn managers(hash_keys: bool) -> (EncryptedDatabaseManager, InMemoryDatabaseManager) {
+        let inner = InMemoryDatabaseManager::new();
+        let manager =
+            EncryptedDatabaseManager::new(Box::new(inner.clone()), &DATABASE_KEY, hash_keys)
+                .unwrap();
+        (manager, inner)
+    }
+
+    #[test]
+    fn round_trip() {
+        for hash_keys in [false, true] {
+            let (manager, inner) = managers(hash_keys);
+            let collection = manager.create_collection("test".into());
+            collection.put("a".into(), b"value".to_vec()).unwrap();
+            assert_eq!(collection.get("a".into()).unwrap(), Some(b"value".to_vec()));
+            let stored = read_all(
+                inner
+                    .create_collection("test".into())
+                    .iter(false, String::new())
+                    .unwrap()
+                    .as_ref(),
+            )
+            .unwrap();
+            assert_eq!(stored.len(), 1);
+            assert_ne!(stored[0].value, b"value".to_vec());
+            assert_eq!(stored[0].key == "a", !hash_keys);
+            collection.del("a".into()).unwrap();
+            assert_eq!(collection.get("a".into()).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn write_batch_is_sealed() {
+        let (manager, inner) = managers(true);
+        let collection = manager.create_collection("test".into());
+        collection.put("b".into(), vec![2]).unwrap();
+        collection
+            .write_batch(vec![
+                WriteOp::Put {
+                    key: "a".into(),
+                    value: vec![1],
+                },
+                WriteOp::Del { key: "b".into() },
+            ])
+            .unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![1]));
+        assert_eq!(collection.get("b".into()).unwrap(), None);
+        assert!(inner
+            .create_collection("test".into())
+            .get("a".into())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn tampered_values_fail() {
+        let (manager, inner) = managers(false);
+        let collection = manager.create_collection("test".into());
+        collection.put("a".into(), vec![1]).unwrap();
+        let stored = inner.create_collection("test".into());
+        let mut sealed = stored.get("a".into()).unwrap().unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        stored.put("a".into(), sealed).unwrap();
+        assert!(matches!(
+            collection.get("a".into()),
+            Err(DbError::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            collection.iter(false, String::new()).unwrap().next(),
+            Err(DbError::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn moved_entries_fail() {
+        for hash_keys in [false, true] {
+            let (manager, inner) = managers(hash_keys);
+            let collection = manager.create_collection("test".into());
+            collection.put("a".into(), vec![1]).unwrap();
+            collection.put("b".into(), vec![2]).unwrap();
+            let cipher = Cipher::new(&DATABASE_KEY, hash_keys).unwrap();
+            let stored = inner.create_collection("test".into());
+            let sealed = stored.get(cipher.stored_key("test", "a")).unwrap().unwrap();
+            stored.put(cipher.stored_key("test", "b"), sealed).unwrap();
+            assert!(matches!(
+                collection.get("b".into()),
+                Err(DbError::AuthenticationFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn entries_are_bound_to_their_collection() {
+        let (manager, inner) = managers(false);
+        manager
+            .create_collection("one".into())
+            .put("a".into(), vec![1])
+            .unwrap();
+        let sealed = inner
+            .create_collection("one".into())
+            .get("a".into())
+            .unwrap()
+            .unwrap();
+        inner
+            .create_collection("two".into())
+            .put("a".into(), sealed)
+            .unwrap();
+        assert!(matches!(
+            manager.create_collection("two".into()).get("a".into()),
+            Err(DbError::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn wrong_database_key_fails() {
+        let (manager, inner) = managers(false);
+        manager
+            .create_collection("test".into())
+            .put("a".into(), vec![1])
+            .unwrap();
+        let other = EncryptedDatabaseManager::new(Box::new(inner), &[8; 32], false).unwrap();
+        assert!(matches!(
+            other.create_collection("test".into()).get("a".into()),
+            Err(DbError::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn hashed_prefix_iteration() {
+        let (manager, _) = managers(true);
+        let collection = manager.create_collection("test".into());
+        let entries = [
+            key(&["subject", "b", "2"]),
+            key(&["subject", "a", "1"]),
+            key(&["subject", "ab", "1"]),
+            key(&["subject", "a", "2"]),
+            key(&["event", "a", "1"]),
+        ];
+        for entry in entries.iter() {
+            collection.put(entry.clone(), vec![]).unwrap();
+        }
+        // Prefix made of whole elements
+        let prefix = key(&["subject", "a", ""]);
+        assert_eq!(
+            keys(collection.iter(false, prefix.clone()).unwrap()),
+            vec![key(&["subject", "a", "1"]), key(&["subject", "a", "2"])]
+        );
+        assert_eq!(
+            keys(collection.iter(true, prefix).unwrap()),
+            vec![key(&["subject", "a", "2"]), key(&["subject", "a", "1"])]
+        );
+        // The last element is incomplete, so it is matched on the decrypted keys
+        assert_eq!(
+            keys(collection.iter(false, key(&["subject", "a"])).unwrap()),
+            vec![
+                key(&["subject", "a", "1"]),
+                key(&["subject", "a", "2"]),
+                key(&["subject", "ab", "1"]),
+            ]
+        );
+        assert_eq!(
+            keys(collection.iter(true, String::new()).unwrap()).len(),
+            entries.len()
+        );
+    }
+
+    #[test]
+    fn invalid_database_key() {
+        assert!(matches!(
+            EncryptedDatabaseManager::new(
+                Box::new(InMemoryDatabaseManager::new()),
+                &[7; 16],
+                false
+            ),
+            Err(SettingsError::InvalidDatabaseKey)
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, RwLock},
+    vec::IntoIter,
+};
+
+use crate::error::DbError;
+
+use super::{
+    DatabaseManagerInterface, DbCollectionInterface, DbCollectionIteratorInterface, Tuple, WriteOp,
+};
+
+type Entries = Arc<RwLock<BTreeMap<String, Vec<u8>>>>;
+
+/// Rust-native database kept in memory. Every collection is an ordered map, so no callback is
+/// crossed on database accesses. Clones share the same data, which lets a node be restarted
+/// on the same database while the manager is alive.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryDatabaseManager {
+    collections: Arc<RwLock<HashMap<String, Entries>>>,
+}
+
+impl InMemoryDatabaseManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes every collection
+    pub fn clear(&self) {
+        if let Ok(mut collections) = self.collections.write() {
+            collections.clear();
+        }
+    }
+}
+
+impl DatabaseManagerInterface for InMemoryDatabaseManager {
+    fn create_collection(&self, identifier: String) -> Box<dyn DbCollectionInterface> {
+        // The core opens the same collection several times, so they must share the entries
+        let entries = match self.collections.write() {
+            Ok(mut collections) => collections.entry(identifier).or_default().clone(),
+            Err(_) => Entries::default(),
+        };
+        Box::new(InMemoryCollection { entries })
+    }
+}
+
+#[derive(Debug)]
+struct InMemoryCollection {
+    entries: Entries,
+}
+
+impl DbCollectionInterface for InMemoryCollection {
+    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError> {
+        let entries = self.entries.read().map_err(|_| DbError::InternalDbError)?;
+        Ok(entries.get(&key).cloned())
+    }
+
+    fn put(&self, key: String, value: Vec<u8>) -> Result<(), DbError> {
+        let mut entries = self.entries.write().map_err(|_| DbError::InternalDbError)?;
+        entries.insert(key, value);
+        Ok(())
+    }
+
+    fn del(&self, key: String) -> Result<(), DbError> {
+        let mut entries = self.entries.write().map_err(|_| DbError::InternalDbError)?;
+        entries.remove(&key);
+        Ok(())
+    }
+
+    fn iter(
+        &self,
+        reverse: bool,
+        prefix: String,
+    ) -> Result<Box<dyn DbCollectionIteratorInterface>, DbError> {
+        // The iterator works on a snapshot, so writes during the iteration don't affect it
+        let entries = self.entries.read().map_err(|_| DbError::InternalDbError)?;
+        let mut tuples: Vec<Tuple> = entries
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, value)| Tuple {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        drop(entries);
+        if reverse {
+            tuples.reverse();
+        }
+        Ok(Box::new(InMemoryIterator {
+            tuples: Mutex::new(tuples.into_iter()),
+        }))
+    }
+
+    fn write_batch(&self, ops: Vec<WriteOp>) -> Result<bool, DbError> {
+        // Readers can't see the batch half applied while the write lock is held
+        let mut entries = self.entries.write().map_err(|_| DbError::InternalDbError)?;
+        for op in ops {
+            match op {
+                WriteOp::Put { key, value } => {
+                    entries.insert(key, value);
+                }
+                WriteOp::Del { key } => {
+                    entries.remove(&key);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug)]
+struct InMemoryIterator {
+    tuples: Mutex<IntoIter<Tuple>>,
+}
+
+impl DbCollectionIteratorInterface for InMemoryIterator {
+    fn next(&self) -> Result<Option<Tuple>, DbError> {
+        let mut tuples = self.tuples.lock().map_err(|_| DbError::InternalDbError)?;
+        Ok(tuples.next())
+    }
+
+    fn next_batch(&self, max: u32) -> Result<Option<Vec<Tuple>>, DbError> {
+        let mut tuples = self.tuples.lock().map_err(|_| DbError::InternalDbError)?;
+        Ok(Some(tuples.by_ref().take(max as usize).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(iterator: Box<dyn DbCollectionIteratorInterface>) -> Vec<String> {
+        let mut keys = Vec::new();
+        while let Some(tuple) = iterator.next().unwrap() {
+            keys.push(tuple.key);
+        }
+        keys
+    }
+
+    fn collection_with(keys: &[&str]) -> Box<dyn DbCollectionInterface> {
+        let collection = InMemoryDatabaseManager::new().create_collection("test".into());
+        for key in keys {
+            collection
+                .put(key.to_string(), key.as_bytes().to_vec())
+                .unwrap();
+        }
+        collection
+    }
+
+    #[test]
+    fn get_put_del() {
+        let collection = collection_with(&[]);
+        assert_eq!(collection.get("a".into()).unwrap(), None);
+        collection.put("a".into(), vec![1]).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![1]));
+        collection.put("a".into(), vec![2]).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![2]));
+        collection.del("a".into()).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), None);
+        // Deleting a missing key isn't an error
+        collection.del("a".into()).unwrap();
+    }
+
+    #[test]
+    fn collections_are_shared_by_identifier() {
+        let manager = InMemoryDatabaseManager::new();
+        manager
+            .create_collection("one".into())
+            .put("a".into(), vec![1])
+            .unwrap();
+        let reopened = manager.clone().create_collection("one".into());
+        assert_eq!(reopened.get("a".into()).unwrap(), Some(vec![1]));
+        let other = manager.create_collection("two".into());
+        assert_eq!(other.get("a".into()).unwrap(), None);
+        manager.clear();
+        let cleared = manager.create_collection("one".into());
+        assert_eq!(cleared.get("a".into()).unwrap(), None);
+    }
+
+    #[test]
+    fn prefix_iteration() {
+        let collection = collection_with(&["a", "a/1", "a/2", "ab", "b/1", "a/3"]);
+        assert_eq!(
+            keys(collection.iter(false, "a/".into()).unwrap()),
+            vec!["a/1", "a/2", "a/3"]
+        );
+        assert_eq!(
+            keys(collection.iter(false, String::new()).unwrap()),
+            vec!["a", "a/1", "a/2", "a/3", "ab", "b/1"]
+        );
+        assert!(keys(collection.iter(false, "c".into()).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn reverse_iteration() {
+        let collection = collection_with(&["a/1", "a/2", "a/3", "b/1"]);
+        assert_eq!(
+            keys(collection.iter(true, "a/".into()).unwrap()),
+            vec!["a/3", "a/2", "a/1"]
+        );
+    }
+
+    #[test]
+    fn batches_and_single_tuples_can_be_mixed() {
+        let collection = collection_with(&["1", "2", "3", "4", "5"]);
+        let iterator = collection.iter(false, String::new()).unwrap();
+        let batch = iterator.next_batch(2).unwrap().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(iterator.next().unwrap().unwrap().key, "3");
+        let batch = iterator.next_batch(10).unwrap().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!(iterator.next_batch(10).unwrap().unwrap().is_empty());
+        assert!(iterator.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn iterator_works_on_a_snapshot() {
+        let collection = collection_with(&["a", "b"]);
+        let iterator = collection.iter(false, String::new()).unwrap();
+        collection.put("c".into(), vec![]).unwrap();
+        collection.del("a".into()).unwrap();
+        assert_eq!(keys(iterator), vec!["a", "b"]);
+        assert_eq!(
+            keys(collection.iter(false, String::new()).unwrap()),
+            vec!["b", "c"]
+        );
+    }
+
+    #[test]
+    fn write_batch_applies_every_operation() {
+        let collection = collection_with(&["a", "b"]);
+        let applied = collection
+            .write_batch(vec![
+                WriteOp::Put {
+                    key: "c".into(),
+                    value: vec![3],
+                },
+                WriteOp::Del { key: "a".into() },
+                WriteOp::Put {
+                    key: "b".into(),
+                    value: vec![2],
+                },
+            ])
+            .unwrap();
+        assert!(applied);
+        assert_eq!(
+            keys(collection.iter(false, String::new()).unwrap()),
+            vec!["b", "c"]
+        );
+        assert_eq!(collection.get("b".into()).unwrap(), Some(vec![2]));
+    }
+
+    #[test]
+    fn write_batch_is_never_seen_half_applied() {
+        let manager = InMemoryDatabaseManager::new();
+        let collection = manager.create_collection("test".into());
+        collection
+            .write_batch(vec![
+                WriteOp::Put {
+                    key: "a".into(),
+                    value: vec![0],
+                },
+                WriteOp::Put {
+                    key: "b".into(),
+                    value: vec![0],
+                },
+            ])
+            .unwrap();
+        let writer = std::thread::spawn(move || {
+            let collection = manager.create_collection("test".into());
+            for value in 1..=200u8 {
+                collection
+                    .write_batch(vec![
+                        WriteOp::Put {
+                            key: "a".into(),
+                            value: vec![value],
+                        },
+                        WriteOp::Put {
+                            key: "b".into(),
+                            value: vec![value],
+                        },
+                    ])
+                    .unwrap();
+            }
+        });
+        while !writer.is_finished() {
+            let iterator = collection.iter(false, String::new()).unwrap();
+            let tuples = iterator.next_batch(2).unwrap().unwrap();
+            assert_eq!(tuples[0].value, tuples[1].value);
+        }
+        writer.join().unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![200]));
+    }
+}
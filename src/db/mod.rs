@@ -0,0 +1,347 @@
+use crate::error::{DbError, SettingsError};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use taple_core::{DatabaseCollection, DatabaseManager};
+
+mod cache;
+pub use cache::DatabaseCacheStats;
+pub(crate) use cache::{CollectionCacheHandle, DatabaseCache};
+mod encrypted;
+use encrypted::EncryptedDatabaseManager;
+mod memory;
+pub use memory::InMemoryDatabaseManager;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteDatabaseManager;
+
+#[derive(Debug)]
+pub struct Tuple {
+    pub key: String,
+    pub value: Vec<u8>,
+}
+
+/// Write applied as part of a batch
+#[derive(Clone, Debug)]
+pub enum WriteOp {
+    Put { key: String, value: Vec<u8> },
+    Del { key: String },
+}
+
+// Tuples requested per callback when the iterator supports batches
+const ITERATOR_BATCH_SIZE: u32 = 64;
+
+pub trait DbCollectionIteratorInterface: Send + Sync + Debug {
+    fn next(&self) -> Result<Option<Tuple>, DbError>;
+    /// Returns up to `max` tuples, and an empty list once the iteration ends. Returns `None`
+    /// if batches aren't supported, in which case `next` is used
+    fn next_batch(&self, _max: u32) -> Result<Option<Vec<Tuple>>, DbError> {
+        Ok(None)
+    }
+}
+
+pub trait DbCollectionInterface: Send + Sync + Debug {
+    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError>;
+    fn put(&self, key: String, value: Vec<u8>) -> Result<(), DbError>;
+    fn del(&self, key: String) -> Result<(), DbError>;
+    fn iter(
+        &self,
+        reverse: bool,
+        prefix: String,
+    ) -> Result<Box<dyn DbCollectionIteratorInterface>, DbError>;
+    /// Applies every operation or none of them. Returns `false` without writing anything if
+    /// the database doesn't support batches, in which case the operations are applied one by one
+    fn write_batch(&self, _ops: Vec<WriteOp>) -> Result<bool, DbError> {
+        Ok(false)
+    }
+}
+
+/// Reads every remaining tuple of the iterator, in batches when it supports them
+pub(crate) fn read_all(
+    iterator: &dyn DbCollectionIteratorInterface,
+) -> Result<Vec<Tuple>, DbError> {
+    let mut tuples = Vec::new();
+    read_batches(iterator, |batch| tuples.extend(batch))?;
+    Ok(tuples)
+}
+
+/// Passes every remaining tuple of the iterator to `f`, a batch at a time. Iterators without
+/// batch support are read with `next` and passed one tuple per batch
+pub(crate) fn read_batches(
+    iterator: &dyn DbCollectionIteratorInterface,
+    mut f: impl FnMut(Vec<Tuple>),
+) -> Result<(), DbError> {
+    let mut batched = true;
+    loop {
+        let batch = match batched {
+            true => iterator.next_batch(ITERATOR_BATCH_SIZE)?,
+            false => None,
+        };
+        match batch {
+            Some(batch) if batch.is_empty() => return Ok(()),
+            Some(batch) => f(batch),
+            None => {
+                batched = false;
+                match iterator.next()? {
+                    Some(tuple) => f(vec![tuple]),
+                    None => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Wraps the host database in the encryption layer when a database key is given
+pub(crate) fn with_encryption(
+    manager: Box<dyn DatabaseManagerInterface>,
+    database_key: Option<&[u8]>,
+    hash_keys: bool,
+) -> Result<Box<dyn DatabaseManagerInterface>, SettingsError> {
+    match database_key {
+        Some(database_key) => Ok(Box::new(EncryptedDatabaseManager::new(
+            manager,
+            database_key,
+            hash_keys,
+        )?)),
+        None if hash_keys => Err(SettingsError::MissingDatabaseKey),
+        None => Ok(manager),
+    }
+}
+
+/// Identifiers of the collections opened by the core, which make up a backup
+pub(crate) type CollectionIdentifiers = Arc<Mutex<BTreeSet<String>>>;
+
+/// Failed database iterations of a node. The core iterators can't return errors, so a failure
+/// ends the iteration and is counted here. The listings compare the count before and after
+/// calling the core and fail instead of returning a truncated result. The iteration runs in a
+/// core task, so a failure can't be traced back to its call, and concurrent listings fail too.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IteratorErrors(Arc<AtomicU64>);
+
+impl IteratorErrors {
+    fn record(&self, collection: &str, error: &DbError) {
+        log::error!("Iteration over collection {} failed: {}", collection, error);
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub struct WrapperIter {
+    inner_iterator: Box<dyn DbCollectionIteratorInterface>,
+    collection: String,
+    iterator_errors: IteratorErrors,
+    finished: bool,
+    /// Cleared once the iterator reports that it doesn't support batches
+    batched: bool,
+    buffer: VecDeque<Tuple>,
+}
+
+impl WrapperIter {
+    fn fetch(&mut self) -> Result<Option<Tuple>, DbError> {
+        if let Some(data) = self.buffer.pop_front() {
+            return Ok(Some(data));
+        }
+        if self.batched {
+            match self.inner_iterator.next_batch(ITERATOR_BATCH_SIZE)? {
+                Some(batch) => {
+                    self.buffer.extend(batch);
+                    return Ok(self.buffer.pop_front());
+                }
+                None => self.batched = false,
+            }
+        }
+        self.inner_iterator.next()
+    }
+}
+
+impl Iterator for WrapperIter {
+    type Item = (String, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.fetch() {
+            Ok(Some(data)) => Some((data.key, data.value)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(error) => {
+                self.finished = true;
+                self.iterator_errors.record(&self.collection, &error);
+                None
+            }
+        }
+    }
+}
+
+pub trait DatabaseManagerInterface: Send + Sync + Debug {
+    fn create_collection(&self, identifier: String) -> Box<dyn DbCollectionInterface>;
+}
+
+/// Database of a running node, shared by the core through `WrapperManager` and by the node
+#[derive(Clone)]
+pub(crate) struct NodeDatabase {
+    pub(crate) manager: Arc<dyn DatabaseManagerInterface>,
+    pub(crate) collections: CollectionIdentifiers,
+    pub(crate) iterator_errors: IteratorErrors,
+    pub(crate) cache: DatabaseCache,
+}
+
+impl NodeDatabase {
+    pub(crate) fn new(manager: Box<dyn DatabaseManagerInterface>, cache_size: u32) -> Self {
+        Self {
+            manager: Arc::from(manager),
+            collections: CollectionIdentifiers::default(),
+            iterator_errors: IteratorErrors::default(),
+            cache: DatabaseCache::new(cache_size),
+        }
+    }
+
+    pub(crate) fn wrapper_manager(&self) -> WrapperManager {
+        WrapperManager {
+            inner_manager: self.manager.clone(),
+            iterator_errors: self.iterator_errors.clone(),
+            cache: self.cache.clone(),
+            collections: self.collections.clone(),
+        }
+    }
+}
+
+pub struct WrapperManager {
+    pub inner_manager: Arc<dyn DatabaseManagerInterface>,
+    pub(crate) iterator_errors: IteratorErrors,
+    pub(crate) cache: DatabaseCache,
+    pub(crate) collections: CollectionIdentifiers,
+}
+
+impl DatabaseManager<WrapperCollection> for WrapperManager {
+    //TODO: Revise default because no implementation can be performed
+    fn default() -> Self {
+        unimplemented!("Don't use the default method, you need to implement one for the platform you are developing for");
+    }
+
+    fn create_collection(&self, identifier: &str) -> WrapperCollection {
+        if let Ok(mut collections) = self.collections.lock() {
+            collections.insert(identifier.to_owned());
+        }
+        WrapperCollection {
+            inner_collection: self.inner_manager.create_collection(identifier.to_owned()),
+            identifier: identifier.to_owned(),
+            iterator_errors: self.iterator_errors.clone(),
+            cache: self.cache.collection(identifier),
+        }
+    }
+}
+
+pub struct WrapperCollection {
+    inner_collection: Box<dyn DbCollectionInterface>,
+    identifier: String,
+    iterator_errors: IteratorErrors,
+    /// `None` when the cache is disabled
+    cache: Option<CollectionCacheHandle>,
+}
+
+impl WrapperCollection {
+    /// Writes `ops` atomically when the database supports batches. Otherwise they are applied
+    /// in order and a failure leaves the previous operations written. Used for the multi-key
+    /// writes of the bridge, such as backup restores. `DatabaseCollection` has no batch method,
+    /// so the writes of the core itself still go through `put` and `del` one key at a time
+    pub(crate) fn write_batch(&self, ops: Vec<WriteOp>) -> Result<(), taple_core::DbError> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        let result = self.inner_collection.write_batch(ops.clone());
+        if let Some(cache) = &self.cache {
+            cache.invalidate(ops.iter().map(|op| match op {
+                WriteOp::Put { key, .. } | WriteOp::Del { key } => key.as_str(),
+            }));
+        }
+        match result {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(err) => return Err(taple_core::DbError::CustomError(err.to_string())),
+        }
+        for op in ops {
+            match op {
+                WriteOp::Put { key, value } => self.put(&key, value)?,
+                WriteOp::Del { key } => self.del(&key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DatabaseCollection for WrapperCollection {
+    fn get(&self, key: &str) -> Result<Vec<u8>, taple_core::DbError> {
+        let generation = match self.cache.as_ref().map(|cache| cache.get(key)) {
+            Some(Ok(data)) => return Ok(data),
+            Some(Err(generation)) => generation,
+            None => None,
+        };
+        match self.inner_collection.get(key.to_string()) {
+            Ok(Some(data)) => {
+                if let (Some(cache), Some(generation)) = (&self.cache, generation) {
+                    cache.insert(generation, key, &data);
+                }
+                return Ok(data);
+            }
+            Ok(None) => return Err(taple_core::DbError::EntryNotFound),
+            Err(err) => return Err(taple_core::DbError::CustomError(err.to_string())),
+        }
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<(), taple_core::DbError> {
+        // Invalidated after writing, as a read started before the write could cache the old value
+        let result = self.inner_collection.put(key.to_string(), data);
+        if let Some(cache) = &self.cache {
+            cache.invalidate([key]);
+        }
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) => return Err(taple_core::DbError::CustomError(err.to_string())),
+        }
+    }
+
+    fn del(&self, key: &str) -> Result<(), taple_core::DbError> {
+        let result = self.inner_collection.del(key.to_string());
+        if let Some(cache) = &self.cache {
+            cache.invalidate([key]);
+        }
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) => return Err(taple_core::DbError::CustomError(err.to_string())),
+        }
+    }
+
+    fn iter<'a>(
+        &'a self,
+        reverse: bool,
+        prefix: String,
+    ) -> Box<dyn Iterator<Item = (String, Vec<u8>)> + 'a> {
+        match self.inner_collection.iter(reverse, prefix) {
+            Ok(inner_iterator) => Box::new(WrapperIter {
+                inner_iterator,
+                collection: self.identifier.clone(),
+                iterator_errors: self.iterator_errors.clone(),
+                finished: false,
+                batched: true,
+                buffer: VecDeque::new(),
+            }),
+            Err(error) => {
+                self.iterator_errors.record(&self.identifier, &error);
+                Box::new(std::iter::empty())
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+
+use crate::error::DbError;
+
+use super::{
+    DatabaseManagerInterface, DbCollectionInterface, DbCollectionIteratorInterface, Tuple, WriteOp,
+};
+
+/// Rust-native SQLite database. Each collection is a table keyed by the entry key, in WAL mode.
+#[derive(Clone, Debug)]
+pub struct SqliteDatabaseManager {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteDatabaseManager {
+    pub fn open(path: &Path) -> Result<Self, rusqlite::Error> {
+        let connection = Connection::open(path)?;
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+}
+
+fn table_name(identifier: &str) -> String {
+    format!("\"collection_{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Smallest key greater than every key that starts with `prefix`, or `None` if there is no
+/// such key. Keys are compared as UTF-8 bytes, which follows the code point order
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut bound: Vec<char> = prefix.chars().collect();
+    while let Some(last) = bound.pop() {
+        let next = match last as u32 + 1 {
+            // Surrogates aren't valid chars
+            0xD800 => Some('\u{E000}'),
+            next => char::from_u32(next),
+        };
+        if let Some(next) = next {
+            bound.push(next);
+            return Some(bound.into_iter().collect());
+        }
+    }
+    None
+}
+
+fn db_error(error: rusqlite::Error) -> DbError {
+    log::error!("SQLite error: {}", error);
+    DbError::InternalDbError
+}
+
+impl DatabaseManagerInterface for SqliteDatabaseManager {
+    fn create_collection(&self, identifier: String) -> Box<dyn DbCollectionInterface> {
+        let table = table_name(&identifier);
+        if let Ok(connection) = self.connection.lock() {
+            // Failures show up in the first access to the collection
+            if let Err(error) = connection.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY NOT NULL, value BLOB NOT NULL) WITHOUT ROWID",
+                table
+            )) {
+                db_error(error);
+            }
+        }
+        Box::new(SqliteCollection {
+            connection: self.connection.clone(),
+            table,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct SqliteCollection {
+    connection: Arc<Mutex<Connection>>,
+    table: String,
+}
+
+impl DbCollectionInterface for SqliteCollection {
+    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| DbError::InternalDbError)?;
+        connection
+            .prepare_cached(&format!("SELECT value FROM {} WHERE key = ?1", self.table))
+            .map_err(db_error)?
+            .query_row(params![key], |row| row.get(0))
+            .optional()
+            .map_err(db_error)
+    }
+
+    fn put(&self, key: String, value: Vec<u8>) -> Result<(), DbError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| DbError::InternalDbError)?;
+        connection
+            .prepare_cached(&format!(
+                "INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)",
+                self.table
+            ))
+            .map_err(db_error)?
+            .execute(params![key, value])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn del(&self, key: String) -> Result<(), DbError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| DbError::InternalDbError)?;
+        connection
+            .prepare_cached(&format!("DELETE FROM {} WHERE key = ?1", self.table))
+            .map_err(db_error)?
+            .execute(params![key])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn iter(
+        &self,
+        reverse: bool,
+        prefix: String,
+    ) -> Result<Box<dyn DbCollectionIteratorInterface>, DbError> {
+        Ok(Box::new(SqliteIterator {
+            connection: self.connection.clone(),
+            table: self.table.clone(),
+            reverse,
+            upper_bound: prefix_upper_bound(&prefix),
+            prefix,
+            cursor: Mutex::new(None),
+        }))
+    }
+
+    fn write_batch(&self, ops: Vec<WriteOp>) -> Result<bool, DbError> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| DbError::InternalDbError)?;
+        // Dropping the transaction without committing rolls it back
+        let transaction = connection.transaction().map_err(db_error)?;
+        for op in ops {
+            match op {
+                WriteOp::Put { key, value } => transaction
+                    .prepare_cached(&format!(
+                        "INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)",
+                        self.table
+                    ))
+                    .and_then(|mut statement| statement.execute(params![key, value]))
+                    .map_err(db_error)?,
+                WriteOp::Del { key } => transaction
+                    .prepare_cached(&format!("DELETE FROM {} WHERE key = ?1", self.table))
+                    .and_then(|mut statement| statement.execute(params![key]))
+                    .map_err(db_error)?,
+            };
+        }
+        transaction.commit().map_err(db_error)?;
+        Ok(true)
+    }
+}
+
+/// Reads the rows of a key range one page at a time, so neither the rows nor the connection
+/// are held between calls. Each page starts after the last key returned, which means writes
+/// made during the iteration are visible if they fall after the current position.
+#[derive(Debug)]
+struct SqliteIterator {
+    connection: Arc<Mutex<Connection>>,
+    table: String,
+    reverse: bool,
+    prefix: String,
+    upper_bound: Option<String>,
+    /// Last key returned, `None` before the first page
+    cursor: Mutex<Option<String>>,
+}
+
+impl SqliteIterator {
+    fn page(&self, max: u32) -> Result<Vec<Tuple>, DbError> {
+        let mut cursor = self.cursor.lock().map_err(|_| DbError::InternalDbError)?;
+        let last_key = (*cursor).clone();
+        let limit = max as i64;
+        // Keys are compared with the BINARY collation, which orders them as the in-memory
+        // database does, and the bounds let SQLite seek the primary key
+        let mut conditions = vec!["key >= ?"];
+        let mut values: Vec<&dyn ToSql> = vec![&self.prefix];
+        if let Some(upper_bound) = &self.upper_bound {
+            conditions.push("key < ?");
+            values.push(upper_bound);
+        }
+        if let Some(last_key) = &last_key {
+            conditions.push(if self.reverse { "key < ?" } else { "key > ?" });
+            values.push(last_key);
+        }
+        values.push(&limit);
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| DbError::InternalDbError)?;
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "SELECT key, value FROM {} WHERE {} ORDER BY key {} LIMIT ?",
+                self.table,
+                conditions.join(" AND "),
+                if self.reverse { "DESC" } else { "ASC" }
+            ))
+            .map_err(db_error)?;
+        let tuples = statement
+            .query_map(values.as_slice(), |row| {
+                Ok(Tuple {
+                    key: row.get(0)?,
+                    value: row.get(1)?,
+                })
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<Tuple>, _>>()
+            .map_err(db_error)?;
+        if let Some(last) = tuples.last() {
+            *cursor = Some(last.key.clone());
+        }
+        Ok(tuples)
+    }
+}
+
+impl DbCollectionIteratorInterface for SqliteIterator {
+    fn next(&self) -> Result<Option<Tuple>, DbError> {
+        Ok(self.page(1)?.pop())
+    }
+
+    fn next_batch(&self, max: u32) -> Result<Option<Vec<Tuple>>, DbError> {
+        Ok(Some(self.page(max)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection_with(keys: &[&str]) -> Box<dyn DbCollectionInterface> {
+        let manager = SqliteDatabaseManager::open(Path::new(":memory:")).unwrap();
+        let collection = manager.create_collection("test".into());
+        for key in keys {
+            collection
+                .put(key.to_string(), key.as_bytes().to_vec())
+                .unwrap();
+        }
+        collection
+    }
+
+    fn keys(iterator: Box<dyn DbCollectionIteratorInterface>) -> Vec<String> {
+        let mut keys = Vec::new();
+        while let Some(tuple) = iterator.next().unwrap() {
+            keys.push(tuple.key);
+        }
+        keys
+    }
+
+    #[test]
+    fn upper_bound_of_prefixes() {
+        assert_eq!(prefix_upper_bound(""), None);
+        assert_eq!(prefix_upper_bound("a/"), Some("a0".to_owned()));
+        assert_eq!(
+            prefix_upper_bound("a\u{D7FF}"),
+            Some("a\u{E000}".to_owned())
+        );
+        assert_eq!(
+            prefix_upper_bound(&format!("a{}", char::MAX)),
+            Some("b".to_owned())
+        );
+        assert_eq!(prefix_upper_bound(&char::MAX.to_string()), None);
+    }
+
+    #[test]
+    fn get_put_del() {
+        let collection = collection_with(&[]);
+        assert_eq!(collection.get("a".into()).unwrap(), None);
+        collection.put("a".into(), vec![1]).unwrap();
+        collection.put("a".into(), vec![2]).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![2]));
+        collection.del("a".into()).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), None);
+    }
+
+    #[test]
+    fn identifiers_are_quoted() {
+        let manager = SqliteDatabaseManager::open(Path::new(":memory:")).unwrap();
+        let collection = manager.create_collection("a\" b".into());
+        collection.put("a".into(), vec![1]).unwrap();
+        assert_eq!(collection.get("a".into()).unwrap(), Some(vec![1]));
+        let other = manager.create_collection("a".into());
+        assert_eq!(other.get("a".into()).unwrap(), None);
+    }
+
+    #[test]
+    fn prefix_iteration_stays_within_the_prefix() {
+        let separator = char::MAX;
+        let nested = format!("a{}1", separator);
+        let collection = collection_with(&["a", "a/1", "a/2", "a0", "ab", nested.as_str(), "b/1"]);
+        assert_eq!(
+            keys(collection.iter(false, "a/".into()).unwrap()),
+            vec!["a/1", "a/2"]
+        );
+        assert_eq!(
+            keys(collection.iter(true, "a/".into()).unwrap()),
+            vec!["a/2", "a/1"]
+        );
+        assert_eq!(
+            keys(collection.iter(false, format!("a{}", separator)).unwrap()),
+            vec![nested.clone()]
+        );
+        assert_eq!(
+            keys(collection.iter(false, String::new()).unwrap()),
+            vec!["a", "a/1", "a/2", "a0", "ab", nested.as_str(), "b/1"]
+        );
+    }
+
+    #[test]
+    fn rows_are_read_in_pages() {
+        let keys: Vec<String> = (0..10).map(|i| format!("k{}", i)).collect();
+        let collection = collection_with(&keys.iter().map(String::as_str).collect::<Vec<_>>());
+        for reverse in [false, true] {
+            let iterator = collection.iter(reverse, "k".into()).unwrap();
+            let mut read = Vec::new();
+            loop {
+                let page = iterator.next_batch(3).unwrap().unwrap();
+                assert!(page.len() <= 3);
+                if page.is_empty() {
+                    break;
+                }
+                read.extend(page.into_iter().map(|tuple| tuple.key));
+            }
+            let mut expected = keys.clone();
+            if reverse {
+                expected.reverse();
+            }
+            assert_eq!(read, expected);
+        }
+    }
+
+    #[test]
+    fn pages_continue_after_the_last_key() {
+        let collection = collection_with(&["k1", "k2", "k4"]);
+        let iterator = collection.iter(false, "k".into()).unwrap();
+        assert_eq!(iterator.next().unwrap().unwrap().key, "k1");
+        collection.put("k3".into(), vec![]).unwrap();
+        collection.put("k0".into(), vec![]).unwrap();
+        assert_eq!(keys(iterator), vec!["k2", "k3", "k4"]);
+    }
+
+    #[test]
+    fn write_batch_is_applied_in_a_transaction() {
+        let collection = collection_with(&["a", "b"]);
+        assert!(collection
+            .write_batch(vec![
+                WriteOp::Put {
+                    key: "c".into(),
+                    value: vec![3],
+                },
+                WriteOp::Del { key: "a".into() },
+            ])
+            .unwrap());
+        assert_eq!(
+            keys(collection.iter(false, String::new()).unwrap()),
+            vec!["b", "c"]
+        );
+    }
+}
@@ -1,33 +1,112 @@
+use taple_core::ApiError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum TapleError {
-    #[error("{0}")]
-    ExecutionError(String),
-    #[error("Node unavailable")]
-    NodeUnavailable,
-    #[error("{0}")]
-    NotFound(String),
+    #[error("{operation}: {message}")]
+    ExecutionError { operation: String, message: String },
+    #[error("{operation}: node unavailable")]
+    NodeUnavailable { operation: String },
+    #[error("{operation}: {message}")]
+    NotFound {
+        operation: String,
+        id: Option<String>,
+        message: String,
+    },
+    #[error("{operation}: invalid parameters: {message}")]
+    InvalidParameters { operation: String, message: String },
+    #[error("{operation}: not enough permissions: {message}")]
+    PermissionDenied { operation: String, message: String },
+    #[error("{operation}: vote not needed: {message}")]
+    VoteNotNeeded {
+        operation: String,
+        id: Option<String>,
+        message: String,
+    },
+    #[error("{operation}: event request could not be processed: {message}")]
+    EventCreationFailed { operation: String, message: String },
+    #[error("{operation}: database error: {message}")]
+    DatabaseError { operation: String, message: String },
+    #[error("{operation}: conflict: {message}")]
+    Conflict {
+        operation: String,
+        id: Option<String>,
+        message: String,
+    },
     #[error("Internal error")]
     InternalError,
-    #[error("Digest Identifier generation failed")]
-    DigestIdentifierGenerationFailed,
-    #[error("Key Identifier generation failed")]
-    KeyIdentifierGenerationFailed,
-    #[error("Signature Identifier generation failed")]
-    SignatureIdentifierGenerationFailed,
-    #[error("Invalid KeyDerivator specified")]
-    InvalidKeyDerivator,
+    #[error("Digest Identifier generation failed: {id}")]
+    DigestIdentifierGenerationFailed { id: String },
+    #[error("Key Identifier generation failed: {id}")]
+    KeyIdentifierGenerationFailed { id: String },
+    #[error("Signature Identifier generation failed: {id}")]
+    SignatureIdentifierGenerationFailed { id: String },
+    #[error("Invalid KeyDerivator specified: {derivator}")]
+    InvalidKeyDerivator { derivator: String },
     #[error("No JSON String")]
     NoJSONString,
-    #[error("Signature generation failed: {0}")]
-    SignatureGenerationFailed(String),
+    #[error("Signature generation failed: {message}")]
+    SignatureGenerationFailed { message: String },
     #[error("Internal lock is poisoned")]
     LockIsPoisoned,
     #[error("Deserialization error")]
     DeserializationError,
     #[error("Incorrect format of governance properties")]
     IncorrectGovernanceProperties,
+    #[error("Request {request_id} did not finish within the given timeout")]
+    RequestTimeout { request_id: String },
+    #[error("{operation}: subject {id} is not active")]
+    SubjectInactive { operation: String, id: String },
+    #[error("Invalid signature from {signer}: {message}")]
+    InvalidSignature { signer: String, message: String },
+    #[error("Invalid key: {message}")]
+    InvalidKey { message: String },
+}
+
+impl TapleError {
+    /// Maps a core [`ApiError`] into the variant of its failure class, keeping the
+    /// operation that failed and, when known, the identifier it was working with.
+    pub(crate) fn from_api_error(operation: &str, id: Option<&str>, error: ApiError) -> Self {
+        let operation = operation.to_owned();
+        let id = id.map(|id| id.to_owned());
+        match error {
+            ApiError::InvalidParameters(message) => Self::InvalidParameters { operation, message },
+            ApiError::NotFound(message) => Self::NotFound {
+                operation,
+                id,
+                message,
+            },
+            ApiError::NotEnoughPermissions(message) => {
+                Self::PermissionDenied { operation, message }
+            }
+            ApiError::VoteNotNeeded(message) => Self::VoteNotNeeded {
+                operation,
+                id,
+                message,
+            },
+            ApiError::DatabaseError(message) => Self::DatabaseError { operation, message },
+            ApiError::ChannelError { .. } => Self::NodeUnavailable { operation },
+            ApiError::EventCreationError { .. } => Self::EventCreationFailed {
+                operation,
+                message: error.to_string(),
+            },
+            ApiError::Conflict { .. } => Self::Conflict {
+                operation,
+                id,
+                message: error.to_string(),
+            },
+            ApiError::SignError { .. } => Self::SignatureGenerationFailed {
+                message: error.to_string(),
+            },
+            // No wildcard, so a new core variant must be classified here before it builds
+            ApiError::UnexpectedError { .. } | ApiError::InternalError { .. } => {
+                Self::ExecutionError {
+                    operation,
+                    message: error.to_string(),
+                }
+            }
+        }
+    }
 }
 
 impl From<uniffi::UnexpectedUniFFICallbackError> for TapleError {
@@ -42,6 +121,8 @@ pub enum DbError {
     KeyElementsError,
     #[error("Internal error")]
     InternalDbError,
+    #[error("Entry could not be authenticated")]
+    AuthenticationFailed,
 }
 
 impl From<uniffi::UnexpectedUniFFICallbackError> for DbError {
@@ -70,6 +151,18 @@ impl From<uniffi::UnexpectedUniFFICallbackError> for NotificationError {
 pub enum SettingsError {
     #[error("Invalid ListenAddr")]
     InvalidListenAddr,
+    #[error("Invalid external address")]
+    InvalidExternalAddress,
+    #[error("Replication factor must be greater than 0 and not greater than 1")]
+    InvalidReplicationFactor,
+    #[error("Timeout must be greater than 0")]
+    InvalidTimeout,
+    #[error("Worker threads must be greater than 0")]
+    InvalidWorkerThreads,
+    #[error("Database key must be 32 bytes long")]
+    InvalidDatabaseKey,
+    #[error("hash_database_keys requires a database_key")]
+    MissingDatabaseKey,
     #[error("Internal Error")]
     InternalError,
 }
@@ -86,6 +179,20 @@ pub enum InitializationError {
     InvalidSettings(String),
     #[error("Start process failed {0}")]
     StartFailed(String),
+    #[error("Invalid private key: {0}")]
+    InvalidKey(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Runtime creation failed: {0}")]
+    RuntimeCreationFailed(String),
+    #[error("Logger already initialized")]
+    LoggerAlreadyInitialized,
+    #[error("Keystore failed: {0}")]
+    KeystoreFailed(String),
+    #[error("A node with controller id {0} is already running")]
+    NodeAlreadyRunning(String),
+    #[error("Database could not be opened: {0}")]
+    DatabaseOpenFailed(String),
     #[error("Internal Error")]
     InternalError,
 }
@@ -96,6 +203,73 @@ impl From<uniffi::UnexpectedUniFFICallbackError> for InitializationError {
     }
 }
 
+impl From<SettingsError> for InitializationError {
+    fn from(error: SettingsError) -> Self {
+        match error {
+            SettingsError::InvalidListenAddr | SettingsError::InvalidExternalAddress => {
+                InitializationError::InvalidAddress(error.to_string())
+            }
+            _ => InitializationError::InvalidSettings(error.to_string()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum KeystoreError {
+    #[error("No key stored in the keystore")]
+    KeyNotFound,
+    #[error("The keystore already holds a key")]
+    KeyAlreadyExists,
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    #[error("Keystore data is corrupted")]
+    CorruptedKeystore,
+    #[error("Key derivation or encryption failed")]
+    CryptoError,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+    #[error("Internal Error")]
+    InternalError,
+}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for KeystoreError {
+    fn from(_: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        Self::InternalError
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Not a backup archive")]
+    InvalidArchive,
+    #[error("Backup archive version not supported")]
+    UnsupportedVersion,
+    #[error("Backup archive checksum mismatch")]
+    ChecksumMismatch,
+    #[error("Backup archive is encrypted and needs a passphrase")]
+    PassphraseRequired,
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    #[error("Collection {0} is not empty")]
+    DatabaseNotEmpty(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+    #[error("Invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("I/O error: {0}")]
+    IoError(String),
+    #[error("Key derivation or encryption failed")]
+    CryptoError,
+    #[error("Internal Error")]
+    InternalError,
+}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for BackupError {
+    fn from(_: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        Self::InternalError
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ShutdownError {
     #[error("Inner lock is poisoned")]
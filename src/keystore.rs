@@ -0,0 +1,370 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use borsh::{BorshDeserialize, BorshSerialize};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use taple_core::crypto::{Ed25519KeyPair, KeyGenerator, KeyMaterial, KeyPair, Secp256k1KeyPair};
+
+use crate::{
+    error::KeystoreError,
+    keys::{controller_id, keypair_from_secret},
+    DatabaseManagerInterface, DbCollectionInterface, TapleKeyDerivator,
+};
+
+const KEYSTORE_COLLECTION: &str = "keystore";
+const NODE_KEY_ENTRY: &str = "node_key";
+const ENVELOPE_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+
+/// Derives a ChaCha20-Poly1305 key from the passphrase with Argon2id
+pub(crate) fn passphrase_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: Params,
+) -> Result<[u8; 32], argon2::Error> {
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+        passphrase.as_bytes(),
+        salt,
+        &mut key,
+    )?;
+    Ok(key)
+}
+
+/// Argon2 parameters read from stored data. Costs above the defaults used to seal keys and
+/// archives are rejected, as they would let a crafted input exhaust the memory or CPU of the
+/// device while deriving the key.
+pub(crate) fn stored_params(m_cost: u32, t_cost: u32, p_cost: u32) -> Option<Params> {
+    if m_cost > Params::DEFAULT_M_COST
+        || t_cost > Params::DEFAULT_T_COST
+        || p_cost > Params::DEFAULT_P_COST
+    {
+        return None;
+    }
+    Params::new(m_cost, t_cost, p_cost, Some(32)).ok()
+}
+
+/// Node key encrypted with ChaCha20-Poly1305 under a key derived from the passphrase with
+/// Argon2id. The header fields are authenticated as associated data.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct KeyEnvelope {
+    version: u8,
+    key_derivator: u8,
+    controller_id: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl KeyEnvelope {
+    fn associated_data(&self) -> Vec<u8> {
+        let mut aad = vec![self.version, self.key_derivator];
+        aad.extend_from_slice(self.controller_id.as_bytes());
+        aad
+    }
+
+    fn derive_key(
+        passphrase: &str,
+        salt: &[u8],
+        params: Params,
+    ) -> Result<[u8; 32], KeystoreError> {
+        passphrase_key(passphrase, salt, params).map_err(|_| KeystoreError::CryptoError)
+    }
+
+    fn seal(keypair: &KeyPair, passphrase: &str) -> Result<Self, KeystoreError> {
+        let params = Params::default();
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let key = Self::derive_key(passphrase, &salt, params.clone())?;
+        let mut envelope = Self {
+            version: ENVELOPE_VERSION,
+            key_derivator: match keypair {
+                KeyPair::Ed25519(_) => 0,
+                KeyPair::Secp256k1(_) => 1,
+            },
+            controller_id: controller_id(keypair),
+            m_cost: params.m_cost(),
+            t_cost: params.t_cost(),
+            p_cost: params.p_cost(),
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            ciphertext: Vec::new(),
+        };
+        let secret_key = keypair.secret_key_bytes();
+        let aad = envelope.associated_data();
+        envelope.ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &secret_key,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KeystoreError::CryptoError)?;
+        Ok(envelope)
+    }
+
+    fn open(&self, passphrase: &str) -> Result<KeyPair, KeystoreError> {
+        if self.version != ENVELOPE_VERSION || self.nonce.len() != 12 {
+            return Err(KeystoreError::CorruptedKeystore);
+        }
+        let key_derivator = match self.key_derivator {
+            0 => TapleKeyDerivator::Ed25519,
+            1 => TapleKeyDerivator::Secp256k1,
+            _ => return Err(KeystoreError::CorruptedKeystore),
+        };
+        let params = stored_params(self.m_cost, self.t_cost, self.p_cost)
+            .ok_or(KeystoreError::CorruptedKeystore)?;
+        let key = Self::derive_key(passphrase, &self.salt, params)?;
+        let aad = self.associated_data();
+        // A wrong passphrase and a tampered envelope can't be told apart
+        let secret_key = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KeystoreError::WrongPassphrase)?;
+        keypair_from_secret(&key_derivator, &secret_key)
+            .map_err(|_| KeystoreError::CorruptedKeystore)
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, KeystoreError> {
+        self.try_to_vec().map_err(|_| KeystoreError::InternalError)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KeystoreError> {
+        Self::try_from_slice(bytes).map_err(|_| KeystoreError::CorruptedKeystore)
+    }
+}
+
+fn collection(manager: &dyn DatabaseManagerInterface) -> Box<dyn DbCollectionInterface> {
+    manager.create_collection(KEYSTORE_COLLECTION.to_owned())
+}
+
+fn read_envelope(
+    collection: &dyn DbCollectionInterface,
+) -> Result<Option<KeyEnvelope>, KeystoreError> {
+    match collection
+        .get(NODE_KEY_ENTRY.to_owned())
+        .map_err(|e| KeystoreError::DatabaseError(e.to_string()))?
+    {
+        Some(bytes) => Ok(Some(KeyEnvelope::from_bytes(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+fn write_envelope(
+    collection: &dyn DbCollectionInterface,
+    envelope: &KeyEnvelope,
+) -> Result<(), KeystoreError> {
+    collection
+        .put(NODE_KEY_ENTRY.to_owned(), envelope.to_bytes()?)
+        .map_err(|e| KeystoreError::DatabaseError(e.to_string()))
+}
+
+/// Decrypts the node key stored in the keystore
+pub(crate) fn load_keypair(
+    manager: &dyn DatabaseManagerInterface,
+    passphrase: &str,
+) -> Result<KeyPair, KeystoreError> {
+    read_envelope(collection(manager).as_ref())?
+        .ok_or(KeystoreError::KeyNotFound)?
+        .open(passphrase)
+}
+
+/// Generates a node key inside the keystore and returns its controller id
+pub fn keystore_create_key(
+    manager: Box<dyn DatabaseManagerInterface>,
+    passphrase: String,
+    key_derivator: TapleKeyDerivator,
+) -> Result<String, KeystoreError> {
+    let collection = collection(manager.as_ref());
+    if read_envelope(collection.as_ref())?.is_some() {
+        return Err(KeystoreError::KeyAlreadyExists);
+    }
+    let keypair = match key_derivator {
+        TapleKeyDerivator::Ed25519 => KeyPair::Ed25519(Ed25519KeyPair::from_seed(&[])),
+        TapleKeyDerivator::Secp256k1 => KeyPair::Secp256k1(Secp256k1KeyPair::from_seed(&[])),
+    };
+    let envelope = KeyEnvelope::seal(&keypair, &passphrase)?;
+    write_envelope(collection.as_ref(), &envelope)?;
+    Ok(envelope.controller_id)
+}
+
+/// Controller id of the stored key. It is readable without the passphrase
+pub fn keystore_get_controller_id(
+    manager: Box<dyn DatabaseManagerInterface>,
+) -> Result<Option<String>, KeystoreError> {
+    Ok(
+        read_envelope(collection(manager.as_ref()).as_ref())?
+            .map(|envelope| envelope.controller_id),
+    )
+}
+
+pub fn keystore_change_passphrase(
+    manager: Box<dyn DatabaseManagerInterface>,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), KeystoreError> {
+    let collection = collection(manager.as_ref());
+    let keypair = read_envelope(collection.as_ref())?
+        .ok_or(KeystoreError::KeyNotFound)?
+        .open(&old_passphrase)?;
+    write_envelope(
+        collection.as_ref(),
+        &KeyEnvelope::seal(&keypair, &new_passphrase)?,
+    )
+}
+
+/// Exports the node key encrypted under `export_passphrase`, to be restored with
+/// `keystore_import` on another device
+pub fn keystore_export(
+    manager: Box<dyn DatabaseManagerInterface>,
+    passphrase: String,
+    export_passphrase: String,
+) -> Result<Vec<u8>, KeystoreError> {
+    let keypair = load_keypair(manager.as_ref(), &passphrase)?;
+    KeyEnvelope::seal(&keypair, &export_passphrase)?.to_bytes()
+}
+
+/// Stores a key exported with `keystore_export` under `passphrase`. Returns its controller id
+pub fn keystore_import(
+    manager: Box<dyn DatabaseManagerInterface>,
+    exported_key: Vec<u8>,
+    export_passphrase: String,
+    passphrase: String,
+) -> Result<String, KeystoreError> {
+    let collection = collection(manager.as_ref());
+    if read_envelope(collection.as_ref())?.is_some() {
+        return Err(KeystoreError::KeyAlreadyExists);
+    }
+    let keypair = KeyEnvelope::from_bytes(&exported_key)?.open(&export_passphrase)?;
+    let envelope = KeyEnvelope::seal(&keypair, &passphrase)?;
+    write_envelope(collection.as_ref(), &envelope)?;
+    Ok(envelope.controller_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryDatabaseManager;
+
+    fn manager(database: &InMemoryDatabaseManager) -> Box<dyn DatabaseManagerInterface> {
+        Box::new(database.clone())
+    }
+
+    #[test]
+    fn stored_params_are_bounded_by_the_defaults() {
+        let params = Params::default();
+        assert!(stored_params(params.m_cost(), params.t_cost(), params.p_cost()).is_some());
+        assert!(stored_params(Params::DEFAULT_M_COST + 1, 2, 1).is_none());
+        assert!(stored_params(Params::DEFAULT_M_COST, Params::DEFAULT_T_COST + 1, 1).is_none());
+        assert!(stored_params(Params::DEFAULT_M_COST, 2, Params::DEFAULT_P_COST + 1).is_none());
+        assert!(stored_params(u32::MAX, u32::MAX, u32::MAX).is_none());
+        // Below the Argon2 minimum
+        assert!(stored_params(0, 2, 1).is_none());
+    }
+
+    #[test]
+    fn created_key_round_trip() {
+        let database = InMemoryDatabaseManager::new();
+        let controller_id = keystore_create_key(
+            manager(&database),
+            "passphrase".into(),
+            TapleKeyDerivator::Ed25519,
+        )
+        .unwrap();
+        assert_eq!(
+            keystore_get_controller_id(manager(&database)).unwrap(),
+            Some(controller_id.clone())
+        );
+        let keypair = load_keypair(&database, "passphrase").unwrap();
+        assert_eq!(crate::keys::controller_id(&keypair), controller_id);
+        assert!(matches!(
+            load_keypair(&database, "wrong"),
+            Err(KeystoreError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            keystore_create_key(
+                manager(&database),
+                "passphrase".into(),
+                TapleKeyDerivator::Secp256k1
+            ),
+            Err(KeystoreError::KeyAlreadyExists)
+        ));
+    }
+
+    #[test]
+    fn empty_keystore() {
+        let database = InMemoryDatabaseManager::new();
+        assert_eq!(
+            keystore_get_controller_id(manager(&database)).unwrap(),
+            None
+        );
+        assert!(matches!(
+            load_keypair(&database, "passphrase"),
+            Err(KeystoreError::KeyNotFound)
+        ));
+    }
+
+    #[test]
+    fn export_and_import_keep_the_key() {
+        let source = InMemoryDatabaseManager::new();
+        let controller_id = keystore_create_key(
+            manager(&source),
+            "passphrase".into(),
+            TapleKeyDerivator::Secp256k1,
+        )
+        .unwrap();
+        let exported =
+            keystore_export(manager(&source), "passphrase".into(), "export".into()).unwrap();
+
+        let target = InMemoryDatabaseManager::new();
+        assert!(matches!(
+            keystore_import(
+                manager(&target),
+                exported.clone(),
+                "wrong".into(),
+                "new".into()
+            ),
+            Err(KeystoreError::WrongPassphrase)
+        ));
+        let imported =
+            keystore_import(manager(&target), exported, "export".into(), "new".into()).unwrap();
+        assert_eq!(imported, controller_id);
+        let keypair = load_keypair(&target, "new").unwrap();
+        assert_eq!(crate::keys::controller_id(&keypair), controller_id);
+    }
+
+    #[test]
+    fn changed_passphrase_replaces_the_old_one() {
+        let database = InMemoryDatabaseManager::new();
+        keystore_create_key(manager(&database), "old".into(), TapleKeyDerivator::Ed25519).unwrap();
+        keystore_change_passphrase(manager(&database), "old".into(), "new".into()).unwrap();
+        assert!(load_keypair(&database, "new").is_ok());
+        assert!(matches!(
+            load_keypair(&database, "old"),
+            Err(KeystoreError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn envelope_with_excessive_costs_is_rejected() {
+        let keypair = KeyPair::Ed25519(Ed25519KeyPair::from_seed(&[]));
+        let mut envelope = KeyEnvelope::seal(&keypair, "passphrase").unwrap();
+        envelope.m_cost = u32::MAX;
+        let envelope = KeyEnvelope::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+        assert!(matches!(
+            envelope.open("passphrase"),
+            Err(KeystoreError::CorruptedKeystore)
+        ));
+    }
+}
@@ -1,33 +1,57 @@
 use std::sync::{Arc, RwLock};
 
-use api::TapleAPI;
+use api::{RequestHandlerInterface, TapleAPI};
 use models::{event::TapleSignedEvent, user_governance::UserGovernance, user_subject::UserSubject};
 use notification::TapleNotification;
 use settings::TapleSettings;
 use taple_core::{
-    crypto::{Ed25519KeyPair, KeyGenerator, KeyMaterial, KeyPair, Secp256k1KeyPair},
-    Node,
+    crypto::{Ed25519KeyPair, KeyGenerator, KeyMaterial, Secp256k1KeyPair},
+    Node, Settings as CoreSettings,
 };
 use tokio::runtime::Runtime;
 mod api;
+mod backup;
 mod db;
 mod error;
+mod keys;
+mod keystore;
+mod logger;
 mod models;
 mod node;
 mod notification;
+mod registry;
 mod settings;
 mod shutdown;
 mod subject_builder;
+mod verification;
 
 pub use shutdown::ShutdownSignal;
 use std::fmt::Debug;
 
-use db::WrapperManager;
+pub use backup::restore_backup;
+use db::NodeDatabase;
+#[cfg(feature = "sqlite")]
+pub use db::SqliteDatabaseManager;
 pub use db::{
-    DatabaseManagerInterface, DbCollectionInterface, DbCollectionIteratorInterface, Tuple,
+    DatabaseCacheStats, DatabaseManagerInterface, DbCollectionInterface,
+    DbCollectionIteratorInterface, InMemoryDatabaseManager, Tuple, WriteOp,
 };
 pub use error::{
-    DbError, InitializationError, NotificationError, SettingsError, ShutdownError, TapleError,
+    BackupError, DbError, InitializationError, KeystoreError, NotificationError, SettingsError,
+    ShutdownError, TapleError,
+};
+pub use keys::{
+    export_key_hex, export_key_mnemonic, export_key_pkcs8_der, export_key_pkcs8_pem,
+    generate_key_from_seed, generate_key_material, import_key, import_key_hex, import_key_mnemonic,
+    import_key_pkcs8_der, import_key_pkcs8_pem,
+};
+pub use keystore::{
+    keystore_change_passphrase, keystore_create_key, keystore_export, keystore_get_controller_id,
+    keystore_import,
+};
+pub use logger::{
+    clear_log_handler, reset_module_log_levels, set_log_handler, set_log_level,
+    set_module_log_level, LogHandlerInterface, TapleLogLevel, TapleLogRecord,
 };
 pub use models::approval::{
     TapleApprovalRequest, TapleApprovalResponse, TapleSignedApprovalRequest,
@@ -35,6 +59,8 @@ pub use models::approval::{
 };
 pub use models::approval_entity::{TapleApprovalEntity, TapleApprovalState};
 pub use models::event::TapleEvent;
+pub use models::identity::TapleIdentity;
+pub use models::key_material::TapleKeyMaterial;
 pub use models::others::{SubjectAndProviders, ValidationProofAndSignatures};
 pub use models::policy::{Policy, Quorum, Validation};
 pub use models::request::{
@@ -44,11 +70,19 @@ pub use models::request::{
 pub use models::role::{Role, RoleEnum, SchemaEnum, Who};
 pub use models::schema::Schema;
 pub use models::signature::TapleSignature;
+pub use models::subject_creation::{SubjectCreationProgress, SubjectCreationStatus};
 pub use models::validation_proof::ValidationProof;
+pub use models::verification::{ChainBreakReason, ChainVerificationReport, ValidationProofReport};
 pub use node::{NotificationHandlerInterface, TapleNode};
-use subject_builder::SubjectBuilder;
+pub use registry::{get_node, get_running_nodes};
+use subject_builder::{SubjectBuilder, SubjectCreation};
+pub use verification::{
+    verify_approval_request_signature, verify_approval_response_signature,
+    verify_event_request_signature, verify_event_signature,
+};
 
 use crate::models::others::TapleKeyDerivator;
+pub use crate::models::others::{TapleDigestDerivator, TaplePassvotation};
 
 pub fn generate_key(key_derivator: TapleKeyDerivator) -> Vec<u8> {
     match key_derivator {
@@ -63,71 +97,150 @@ pub fn generate_key(key_derivator: TapleKeyDerivator) -> Vec<u8> {
     }
 }
 
+/// Verifies a validation proof without a running node. `governance_properties` is the JSON state
+/// of the governance at `governance_version`, as returned by `UserGovernance::get_properties`.
+pub fn verify_validation_proof(
+    proof_and_signatures: ValidationProofAndSignatures,
+    governance_properties: String,
+    governance_version: u64,
+) -> Result<ValidationProofReport, TapleError> {
+    let properties: serde_json::Value =
+        serde_json::from_str(&governance_properties).map_err(|_| TapleError::NoJSONString)?;
+    verification::verify_validation_proof(proof_and_signatures, &properties, governance_version)
+}
+
 /// Funcion de start
 
 pub fn start(
     manager: Box<dyn DatabaseManagerInterface>,
     settings: TapleSettings,
 ) -> Result<Arc<TapleNode>, InitializationError> {
-    #[cfg(feature = "android")]
-    android_logger::init_once(
-        android_logger::Config::default()
-            .with_max_level(log::LevelFilter::Debug)
-            .with_tag("taple"),
-    );
-    #[cfg(feature = "android")]
-    log::debug!("Android Rust logger running");
-
-    #[cfg(feature = "ios")]
-    oslog::OsLogger::new("com.opencanarias.taple")
-        .level_filter(log::LevelFilter::Debug)
-        .category_level_filter("Settings", log::LevelFilter::Debug)
-        .init()
-        .unwrap();
-    #[cfg(feature = "ios")]
-    log::debug!("IOS Rust logger running");
-
-    let keypair = match &settings.key_derivator {
-        TapleKeyDerivator::Ed25519 => {
-            KeyPair::Ed25519(Ed25519KeyPair::from_secret_key(&settings.private_key))
-        }
-        TapleKeyDerivator::Secp256k1 => {
-            KeyPair::Secp256k1(Secp256k1KeyPair::from_secret_key(&settings.private_key))
-        }
+    logger::init_for_node();
+    log::debug!("Rust logger running");
+
+    let keypair = keys::keypair_from_secret(&settings.key_derivator, &settings.private_key)
+        .map_err(|e| InitializationError::InvalidKey(e.to_string()))?;
+    let controller_id = keys::controller_id(&keypair);
+    if registry::is_running(&controller_id) {
+        return Err(InitializationError::NodeAlreadyRunning(controller_id));
+    }
+
+    let manager = db::with_encryption(
+        manager,
+        settings.database_key.as_deref(),
+        settings.hash_database_keys.unwrap_or(false),
+    )?;
+
+    let database_cache_size = settings.database_cache_size.unwrap_or(0);
+    let rt: Arc<Runtime> = settings.runtime()?;
+    let current_thread = settings.is_current_thread();
+    let thread_name = settings.runtime_thread_name();
+    // Records from a shared runtime can't be attributed to a single node
+    let log_tag = match settings.is_shared_runtime() {
+        true => None,
+        false => settings.log_tag.clone(),
     };
+    let log_thread_name = log_tag.as_ref().and(thread_name.clone());
+    if let Some(log_thread_name) = &log_thread_name {
+        logger::check_thread_tag(log_thread_name)?;
+    }
 
-    let settings = settings.try_into().unwrap();
-    // .map_err(|e| InitializationError::InvalidSettings(e.to_string()))?;
+    let settings: CoreSettings = settings.try_into()?;
 
     let (sx, mut rx) = tokio::sync::mpsc::channel::<()>(10);
 
-    let rt: Arc<Runtime> = Arc::new(tokio::runtime::Runtime::new().unwrap());
+    // A current-thread runtime only runs the node tasks while some thread is inside `block_on`,
+    // so a dedicated thread drives it until the node is dropped
+    let runtime_driver = if current_thread {
+        let (driver_sx, driver_rx) = tokio::sync::oneshot::channel::<()>();
+        let driver_rt = rt.clone();
+        std::thread::Builder::new()
+            .name(thread_name.unwrap_or_else(|| "taple-runtime".to_owned()))
+            .spawn(move || {
+                driver_rt.block_on(async {
+                    let _ = driver_rx.await;
+                });
+            })
+            .map_err(|e| InitializationError::RuntimeCreationFailed(e.to_string()))?;
+        Some(driver_sx)
+    } else {
+        None
+    };
 
     let rt_start = rt.clone();
+    let database = NodeDatabase::new(manager, database_cache_size);
 
-    rt_start.block_on(async {
-        match Node::build(
-            settings,
-            WrapperManager {
-                inner_manager: manager,
-            },
-        ) {
+    let node = rt_start.block_on(async {
+        match Node::build(settings, database.wrapper_manager()) {
             Ok((taple, api)) => {
                 taple.bind_with_shutdown(Box::pin(async move {
                     rx.recv().await;
                 }));
-                let node = Arc::new(TapleNode::new(
-                    sx,
-                    api,
-                    keypair.clone(),
-                    RwLock::new(Some(taple)),
-                    rt.clone(),
-                ));
+                let node = Arc::new_cyclic(|weak_self| {
+                    TapleNode::new(
+                        sx,
+                        api,
+                        keypair.clone(),
+                        RwLock::new(Some(taple)),
+                        rt.clone(),
+                        runtime_driver,
+                        controller_id.clone(),
+                        log_thread_name.clone(),
+                        database,
+                        weak_self.clone(),
+                    )
+                });
                 Ok(node)
             }
             Err(error) => Err(InitializationError::StartFailed(error.to_string())),
         }
-    })
+    })?;
+    registry::register(&controller_id, &node)?;
+    if let (Some(log_tag), Some(log_thread_name)) = (log_tag, log_thread_name) {
+        logger::register_thread_tag(log_thread_name, controller_id, log_tag)?;
+    }
+    Ok(node)
+}
+
+/// Starts a node on a Rust-native in-memory database. Passing the same `manager` again restarts
+/// a node on the data of the previous one; without it a new empty database is used.
+pub fn start_in_memory(
+    settings: TapleSettings,
+    manager: Option<Arc<InMemoryDatabaseManager>>,
+) -> Result<Arc<TapleNode>, InitializationError> {
+    let manager = match manager {
+        Some(manager) => manager.as_ref().clone(),
+        None => InMemoryDatabaseManager::new(),
+    };
+    start(Box::new(manager), settings)
+}
+
+/// Starts a node on a SQLite database at `path`, created if it doesn't exist. Only available
+/// with the `sqlite` feature.
+#[cfg(feature = "sqlite")]
+#[uniffi::export]
+pub fn start_with_sqlite(
+    path: String,
+    settings: TapleSettings,
+) -> Result<Arc<TapleNode>, InitializationError> {
+    let manager = SqliteDatabaseManager::open(std::path::Path::new(&path))
+        .map_err(|e| InitializationError::DatabaseOpenFailed(e.to_string()))?;
+    start(Box::new(manager), settings)
+}
+
+/// Starts the node with the key stored in the keystore. `settings.private_key` and
+/// `settings.key_derivator` are ignored. Like the keystore functions, it only takes a host
+/// database manager, not the native in-memory or SQLite ones.
+pub fn start_with_keystore(
+    manager: Box<dyn DatabaseManagerInterface>,
+    passphrase: String,
+    mut settings: TapleSettings,
+) -> Result<Arc<TapleNode>, InitializationError> {
+    let keypair = keystore::load_keypair(manager.as_ref(), &passphrase)
+        .map_err(|e| InitializationError::KeystoreFailed(e.to_string()))?;
+    settings.key_derivator = keypair.get_key_derivator().into();
+    settings.private_key = keypair.secret_key_bytes();
+    start(manager, settings)
 }
 
 uniffi::include_scaffolding!("taple_uniffi");
@@ -0,0 +1,319 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::InitializationError;
+
+pub trait LogHandlerInterface: Send + Sync {
+    fn log(&self, record: TapleLogRecord);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TapleLogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<Level> for TapleLogLevel {
+    fn from(value: Level) -> Self {
+        match value {
+            Level::Error => Self::Error,
+            Level::Warn => Self::Warn,
+            Level::Info => Self::Info,
+            Level::Debug => Self::Debug,
+            Level::Trace => Self::Trace,
+        }
+    }
+}
+
+impl Into<LevelFilter> for TapleLogLevel {
+    fn into(self) -> LevelFilter {
+        match self {
+            TapleLogLevel::Off => LevelFilter::Off,
+            TapleLogLevel::Error => LevelFilter::Error,
+            TapleLogLevel::Warn => LevelFilter::Warn,
+            TapleLogLevel::Info => LevelFilter::Info,
+            TapleLogLevel::Debug => LevelFilter::Debug,
+            TapleLogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TapleLogRecord {
+    /// Module that emitted the record, e.g. `taple_core::ledger`
+    pub target: String,
+    pub level: TapleLogLevel,
+    pub message: String,
+    /// `log_tag` of the node whose runtime emitted the record
+    pub tag: Option<String>,
+}
+
+/// Logger installed by the crate. Records that pass the level filters are sent to the platform
+/// logger (`android`/`ios` features) and to the handler registered by the host, if any.
+struct HostLogger {
+    installed: Mutex<bool>,
+    default_level: RwLock<LevelFilter>,
+    module_levels: RwLock<Vec<(String, LevelFilter)>>,
+    /// Cloned out of the lock before being called, so the handler can replace or clear itself
+    handler: RwLock<Option<Arc<dyn LogHandlerInterface>>>,
+    platform_loggers: RwLock<Vec<Box<dyn Log>>>,
+    /// Node log tags: runtime thread name, controller id of the node and tag
+    thread_tags: RwLock<Vec<(String, String, String)>>,
+}
+
+static HOST_LOGGER: HostLogger = HostLogger {
+    installed: Mutex::new(false),
+    default_level: RwLock::new(LevelFilter::Debug),
+    module_levels: RwLock::new(Vec::new()),
+    handler: RwLock::new(None),
+    platform_loggers: RwLock::new(Vec::new()),
+    thread_tags: RwLock::new(Vec::new()),
+};
+
+impl HostLogger {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        // The most specific module prefix wins
+        let default_level = self
+            .default_level
+            .read()
+            .map(|level| *level)
+            .unwrap_or(LevelFilter::Debug);
+        let Ok(module_levels) = self.module_levels.read() else {
+            return default_level;
+        };
+        module_levels
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || (target.starts_with(module.as_str())
+                        && target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(default_level)
+    }
+
+    fn current_tag(&self) -> Option<String> {
+        let thread = std::thread::current();
+        let thread_name = thread.name()?;
+        let thread_tags = self.thread_tags.read().ok()?;
+        thread_tags
+            .iter()
+            .find(|(name, _, _)| name == thread_name)
+            .map(|(_, _, tag)| tag.clone())
+    }
+
+    fn update_max_level(&self) {
+        let mut max_level = self
+            .default_level
+            .read()
+            .map(|level| *level)
+            .unwrap_or(LevelFilter::Debug);
+        if let Ok(module_levels) = self.module_levels.read() {
+            for (_, level) in module_levels.iter() {
+                max_level = max_level.max(*level);
+            }
+        }
+        log::set_max_level(max_level);
+    }
+}
+
+impl Log for HostLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(platform_loggers) = self.platform_loggers.read() {
+            for logger in platform_loggers.iter() {
+                logger.log(record);
+            }
+        }
+        let handler = match self.handler.read() {
+            Ok(handler) => handler.clone(),
+            Err(_) => None,
+        };
+        if let Some(handler) = handler {
+            handler.log(TapleLogRecord {
+                target: record.target().to_owned(),
+                level: record.level().into(),
+                message: record.args().to_string(),
+                tag: self.current_tag(),
+            });
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(platform_loggers) = self.platform_loggers.read() {
+            for logger in platform_loggers.iter() {
+                logger.flush();
+            }
+        }
+    }
+}
+
+/// Installs the crate logger for a starting node. A logger already installed by the host
+/// process is kept, as `start` worked alongside one before; the records then go to that logger
+/// and `set_log_handler` is unavailable.
+pub(crate) fn init_for_node() {
+    if let Err(InitializationError::LoggerAlreadyInitialized) = init() {
+        log::warn!("A logger is already installed, the TAPLE logger is not used");
+    }
+}
+
+/// Installs the crate logger as the `log` backend. Calling it again is a no-op; it only fails if
+/// the process already has a logger that is not ours.
+pub(crate) fn init() -> Result<(), InitializationError> {
+    let mut installed = HOST_LOGGER
+        .installed
+        .lock()
+        .map_err(|_| InitializationError::InternalError)?;
+    if *installed {
+        return Ok(());
+    }
+    log::set_logger(&HOST_LOGGER).map_err(|_| InitializationError::LoggerAlreadyInitialized)?;
+    *installed = true;
+
+    let mut platform_loggers = HOST_LOGGER
+        .platform_loggers
+        .write()
+        .map_err(|_| InitializationError::InternalError)?;
+    #[cfg(feature = "android")]
+    platform_loggers.push(Box::new(android_logger::AndroidLogger::new(
+        android_logger::Config::default()
+            .with_max_level(LevelFilter::Trace)
+            .with_tag("taple"),
+    )));
+    #[cfg(feature = "ios")]
+    platform_loggers.push(Box::new(
+        oslog::OsLogger::new("com.opencanarias.taple").level_filter(LevelFilter::Trace),
+    ));
+    drop(platform_loggers);
+
+    HOST_LOGGER.update_max_level();
+    Ok(())
+}
+
+fn check_thread_name(
+    thread_tags: &[(String, String, String)],
+    thread_name: &str,
+) -> Result<(), InitializationError> {
+    match thread_tags.iter().any(|(name, _, _)| name == thread_name) {
+        true => Err(InitializationError::InvalidSettings(format!(
+            "Runtime thread name {} is already used by another node",
+            thread_name
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// Fails if the records of the runtime threads named `thread_name` are already tagged, as the
+/// records of both nodes couldn't be told apart
+pub(crate) fn check_thread_tag(thread_name: &str) -> Result<(), InitializationError> {
+    let read_lock = HOST_LOGGER
+        .thread_tags
+        .read()
+        .map_err(|_| InitializationError::InternalError)?;
+    check_thread_name(&read_lock, thread_name)
+}
+
+/// Tags the records emitted from the runtime threads named `thread_name` with the tag of the
+/// node `controller_id`
+pub(crate) fn register_thread_tag(
+    thread_name: String,
+    controller_id: String,
+    tag: String,
+) -> Result<(), InitializationError> {
+    let mut write_lock = HOST_LOGGER
+        .thread_tags
+        .write()
+        .map_err(|_| InitializationError::InternalError)?;
+    check_thread_name(&write_lock, &thread_name)?;
+    write_lock.push((thread_name, controller_id, tag));
+    Ok(())
+}
+
+pub(crate) fn unregister_thread_tag(thread_name: &str, controller_id: &str) {
+    if let Ok(mut write_lock) = HOST_LOGGER.thread_tags.write() {
+        write_lock.retain(|(name, id, _)| !(name == thread_name && id == controller_id));
+    }
+}
+
+/// Registers the host log sink. Replaces the previous one. Fails with `LoggerAlreadyInitialized`
+/// if the process installed its own logger
+pub fn set_log_handler(handler: Box<dyn LogHandlerInterface>) -> Result<(), InitializationError> {
+    init()?;
+    let mut write_lock = HOST_LOGGER
+        .handler
+        .write()
+        .map_err(|_| InitializationError::InternalError)?;
+    *write_lock = Some(Arc::from(handler));
+    Ok(())
+}
+
+pub fn clear_log_handler() {
+    if let Ok(mut write_lock) = HOST_LOGGER.handler.write() {
+        *write_lock = None;
+    }
+}
+
+/// Level applied to the modules without a specific level. Defaults to `Debug`
+pub fn set_log_level(level: TapleLogLevel) {
+    if let Ok(mut write_lock) = HOST_LOGGER.default_level.write() {
+        *write_lock = level.into();
+    }
+    HOST_LOGGER.update_max_level();
+}
+
+/// Sets the level of a module and its submodules, e.g. `taple_core::network`
+pub fn set_module_log_level(module: String, level: TapleLogLevel) {
+    if let Ok(mut write_lock) = HOST_LOGGER.module_levels.write() {
+        write_lock.retain(|(existing, _)| *existing != module);
+        write_lock.push((module, level.into()));
+    }
+    HOST_LOGGER.update_max_level();
+}
+
+pub fn reset_module_log_levels() {
+    if let Ok(mut write_lock) = HOST_LOGGER.module_levels.write() {
+        write_lock.clear();
+    }
+    HOST_LOGGER.update_max_level();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const TARGET: &str = "taple_ffi::logger::tests";
+
+    /// Clears itself on the first record of the test. Records logged by other tests are ignored
+    struct ClearingHandler(Arc<AtomicUsize>);
+
+    impl LogHandlerInterface for ClearingHandler {
+        fn log(&self, record: TapleLogRecord) {
+            if record.target == TARGET {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                clear_log_handler();
+            }
+        }
+    }
+
+    #[test]
+    fn handler_can_clear_itself() {
+        let records = Arc::new(AtomicUsize::new(0));
+        set_log_handler(Box::new(ClearingHandler(records.clone()))).unwrap();
+        log::error!(target: TARGET, "first");
+        log::error!(target: TARGET, "second");
+        assert_eq!(records.load(Ordering::SeqCst), 1);
+    }
+}
@@ -40,15 +40,24 @@ impl TryInto<ApprovalRequest> for TapleApprovalRequest {
             event_request: self.event_request.try_into()?,
             sn: self.sn,
             gov_version: self.gov_version,
-            gov_id: DigestIdentifier::from_str(&self.gov_id)
-                .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+            gov_id: DigestIdentifier::from_str(&self.gov_id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.gov_id.to_owned(),
+                }
+            })?,
             patch: ValueWrapper(
                 serde_json::from_str(&self.patch).map_err(|_| TapleError::NoJSONString)?,
             ),
-            state_hash: DigestIdentifier::from_str(&self.state_hash)
-                .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
-            hash_prev_event: DigestIdentifier::from_str(&self.hash_prev_event)
-                .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+            state_hash: DigestIdentifier::from_str(&self.state_hash).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.state_hash.to_owned(),
+                }
+            })?,
+            hash_prev_event: DigestIdentifier::from_str(&self.hash_prev_event).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.hash_prev_event.to_owned(),
+                }
+            })?,
         })
     }
 }
@@ -121,8 +130,11 @@ impl TryInto<ApprovalResponse> for TapleApprovalResponse {
     type Error = TapleError;
     fn try_into(self) -> Result<ApprovalResponse, Self::Error> {
         Ok(ApprovalResponse {
-            appr_req_hash: DigestIdentifier::from_str(&self.appr_req_hash)
-                .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+            appr_req_hash: DigestIdentifier::from_str(&self.appr_req_hash).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.appr_req_hash.to_owned(),
+                }
+            })?,
             approved: self.approved,
         })
     }
@@ -20,6 +20,17 @@ impl From<ApprovalState> for TapleApprovalState {
     }
 }
 
+impl Into<ApprovalState> for TapleApprovalState {
+    fn into(self) -> ApprovalState {
+        match self {
+            TapleApprovalState::Pending => ApprovalState::Pending,
+            TapleApprovalState::RespondedAccepted => ApprovalState::RespondedAccepted,
+            TapleApprovalState::RespondedRejected => ApprovalState::RespondedRejected,
+            TapleApprovalState::Obsolete => ApprovalState::Obsolete,
+        }
+    }
+}
+
 pub struct TapleApprovalEntity {
     pub id: String,
     pub request: TapleSignedApprovalRequest,
@@ -1,8 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
-use taple_core::{signature::Signed, Derivable, Event};
+use taple_core::{
+    signature::{Signature, Signed},
+    Derivable, DigestIdentifier, Event, ValueWrapper,
+};
 
-use crate::{TapleSignature, TapleSignedEventRequest};
+use crate::{TapleError, TapleSignature, TapleSignedEventRequest};
 
 #[derive(Clone, Debug)]
 pub struct TapleEvent {
@@ -50,6 +56,51 @@ impl From<Event> for TapleEvent {
     }
 }
 
+impl TryInto<Event> for TapleEvent {
+    type Error = TapleError;
+    fn try_into(self) -> Result<Event, Self::Error> {
+        let mut evaluators: HashSet<Signature> = HashSet::new();
+        let mut approvers: HashSet<Signature> = HashSet::new();
+
+        for (_, x) in self.evaluators {
+            evaluators.insert(x.try_into()?);
+        }
+
+        for (_, x) in self.approvers {
+            approvers.insert(x.try_into()?);
+        }
+
+        Ok(Event {
+            subject_id: DigestIdentifier::from_str(&self.subject_id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.subject_id.to_owned(),
+                }
+            })?,
+            event_request: self.event_request.try_into()?,
+            sn: self.sn,
+            gov_version: self.gov_version,
+            patch: ValueWrapper(
+                serde_json::from_str(&self.patch).map_err(|_| TapleError::NoJSONString)?,
+            ),
+            state_hash: DigestIdentifier::from_str(&self.state_hash).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.state_hash.to_owned(),
+                }
+            })?,
+            eval_success: self.eval_success,
+            appr_required: self.appr_required,
+            approved: self.approved,
+            hash_prev_event: DigestIdentifier::from_str(&self.hash_prev_event).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.hash_prev_event.to_owned(),
+                }
+            })?,
+            evaluators,
+            approvers,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TapleSignedEvent {
     pub content: TapleEvent,
@@ -64,3 +115,13 @@ impl From<Signed<Event>> for TapleSignedEvent {
         }
     }
 }
+
+impl TryInto<Signed<Event>> for TapleSignedEvent {
+    type Error = TapleError;
+    fn try_into(self) -> Result<Signed<Event>, Self::Error> {
+        Ok(Signed {
+            content: self.content.try_into()?,
+            signature: self.signature.try_into()?,
+        })
+    }
+}
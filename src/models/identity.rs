@@ -0,0 +1,11 @@
+use super::others::TapleKeyDerivator;
+
+#[derive(Clone, Debug)]
+pub struct TapleIdentity {
+    /// `KeyIdentifier` of the node, as it appears in the governance members
+    pub controller_id: String,
+    pub public_key: Vec<u8>,
+    pub key_derivator: TapleKeyDerivator,
+    /// libp2p peer id used by the node in the network
+    pub peer_id: String,
+}
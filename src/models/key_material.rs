@@ -0,0 +1,11 @@
+use super::others::TapleKeyDerivator;
+
+#[derive(Clone, Debug)]
+pub struct TapleKeyMaterial {
+    /// Raw secret key, as expected by `TapleSettings.private_key`
+    pub secret_key: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub key_derivator: TapleKeyDerivator,
+    /// `KeyIdentifier` derived from the public key
+    pub controller_id: String,
+}
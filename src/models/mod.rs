@@ -1,12 +1,16 @@
 pub mod approval;
 pub mod approval_entity;
 pub mod event;
+pub mod identity;
+pub mod key_material;
 pub mod others;
+pub mod policy;
 pub mod request;
+pub mod role;
+pub mod schema;
 pub mod signature;
+pub mod subject_creation;
 pub mod user_governance;
 pub mod user_subject;
 pub mod validation_proof;
-pub mod policy;
-pub mod role;
-pub mod schema;
+pub mod verification;
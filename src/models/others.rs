@@ -1,37 +1,81 @@
-use taple_core::KeyDerivator;
+use taple_core::{DigestDerivator, KeyDerivator};
 
 use super::signature::TapleSignature;
 use super::validation_proof::ValidationProof;
 
 pub struct SubjectAndProviders {
-  pub subject_id: String,
-  pub providers: Vec<String>
+    pub subject_id: String,
+    pub providers: Vec<String>,
 }
 
 pub struct ValidationProofAndSignatures {
-  pub validation_proof: ValidationProof,
-  pub signatures: Vec<TapleSignature>
+    pub validation_proof: ValidationProof,
+    pub signatures: Vec<TapleSignature>,
 }
 
+#[derive(Clone, Copy, Debug)]
 pub enum TapleKeyDerivator {
-  Ed25519,
-  Secp256k1,
+    Ed25519,
+    Secp256k1,
 }
 
 impl From<KeyDerivator> for TapleKeyDerivator {
-  fn from(value: KeyDerivator) -> Self {
-      match value {
-          KeyDerivator::Ed25519 => Self::Ed25519,
-          KeyDerivator::Secp256k1 => Self::Secp256k1,
-      }
-  }
+    fn from(value: KeyDerivator) -> Self {
+        match value {
+            KeyDerivator::Ed25519 => Self::Ed25519,
+            KeyDerivator::Secp256k1 => Self::Secp256k1,
+        }
+    }
 }
 
 impl Into<KeyDerivator> for TapleKeyDerivator {
-  fn into(self) -> KeyDerivator {
-      match self {
-          TapleKeyDerivator::Ed25519 => KeyDerivator::Ed25519,
-          TapleKeyDerivator::Secp256k1 => KeyDerivator::Secp256k1,
-      }
-  }
-}
\ No newline at end of file
+    fn into(self) -> KeyDerivator {
+        match self {
+            TapleKeyDerivator::Ed25519 => KeyDerivator::Ed25519,
+            TapleKeyDerivator::Secp256k1 => KeyDerivator::Secp256k1,
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug)]
+pub enum TapleDigestDerivator {
+    Blake3_256,
+    Blake3_512,
+    SHA2_256,
+    SHA2_512,
+    SHA3_256,
+    SHA3_512,
+}
+
+impl Into<DigestDerivator> for TapleDigestDerivator {
+    fn into(self) -> DigestDerivator {
+        match self {
+            TapleDigestDerivator::Blake3_256 => DigestDerivator::Blake3_256,
+            TapleDigestDerivator::Blake3_512 => DigestDerivator::Blake3_512,
+            TapleDigestDerivator::SHA2_256 => DigestDerivator::SHA2_256,
+            TapleDigestDerivator::SHA2_512 => DigestDerivator::SHA2_512,
+            TapleDigestDerivator::SHA3_256 => DigestDerivator::SHA3_256,
+            TapleDigestDerivator::SHA3_512 => DigestDerivator::SHA3_512,
+        }
+    }
+}
+
+/// Automatic vote of the node on approval requests
+#[derive(Clone, Copy, Debug)]
+pub enum TaplePassvotation {
+    /// Approval requests are left pending until the user votes
+    Disabled,
+    AlwaysAccept,
+    AlwaysReject,
+}
+
+impl Into<u8> for TaplePassvotation {
+    fn into(self) -> u8 {
+        match self {
+            TaplePassvotation::Disabled => 0,
+            TaplePassvotation::AlwaysAccept => 1,
+            TaplePassvotation::AlwaysReject => 2,
+        }
+    }
+}
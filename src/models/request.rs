@@ -84,20 +84,29 @@ impl TryInto<TapleEventRequestType> for EventRequestType {
                 name,
                 public_key,
             } => TapleEventRequestType::Create(TapleCreateRequest {
-                governance_id: DigestIdentifier::from_str(&governance_id)
-                    .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                governance_id: DigestIdentifier::from_str(&governance_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: governance_id.to_owned(),
+                    }
+                })?,
                 schema_id: schema_id,
                 namespace: namespace,
                 name: name,
-                public_key: KeyIdentifier::from_str(&public_key)
-                    .map_err(|_| TapleError::KeyIdentifierGenerationFailed)?,
+                public_key: KeyIdentifier::from_str(&public_key).map_err(|_| {
+                    TapleError::KeyIdentifierGenerationFailed {
+                        id: public_key.to_owned(),
+                    }
+                })?,
             }),
             Self::Fact {
                 subject_id,
                 payload,
             } => TapleEventRequestType::Fact(TapleFactRequest {
-                subject_id: DigestIdentifier::from_str(&subject_id)
-                    .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                subject_id: DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: subject_id.to_owned(),
+                    }
+                })?,
                 payload: ValueWrapper(
                     serde_json::from_str(&payload).map_err(|_| TapleError::NoJSONString)?,
                 ),
@@ -106,14 +115,23 @@ impl TryInto<TapleEventRequestType> for EventRequestType {
                 subject_id,
                 public_key,
             } => TapleEventRequestType::Transfer(TapleTransferRequest {
-                subject_id: DigestIdentifier::from_str(&subject_id)
-                    .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
-                public_key: KeyIdentifier::from_str(&public_key)
-                    .map_err(|_| TapleError::KeyIdentifierGenerationFailed)?,
+                subject_id: DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: subject_id.to_owned(),
+                    }
+                })?,
+                public_key: KeyIdentifier::from_str(&public_key).map_err(|_| {
+                    TapleError::KeyIdentifierGenerationFailed {
+                        id: public_key.to_owned(),
+                    }
+                })?,
             }),
             Self::EOL { subject_id } => TapleEventRequestType::EOL(TapleEOLRequest {
-                subject_id: DigestIdentifier::from_str(&subject_id)
-                    .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+                subject_id: DigestIdentifier::from_str(&subject_id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed {
+                        id: subject_id.to_owned(),
+                    }
+                })?,
             }),
         })
     }
@@ -203,13 +221,15 @@ impl TryInto<CoreTapleRequest> for TapleRequest {
     type Error = TapleError;
     fn try_into(self) -> Result<CoreTapleRequest, Self::Error> {
         Ok(CoreTapleRequest {
-            id: DigestIdentifier::from_str(&self.id)
-                .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
+            id: DigestIdentifier::from_str(&self.id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.id.to_owned(),
+                }
+            })?,
             subject_id: if let Some(id) = self.subject_id {
-                Some(
-                    DigestIdentifier::from_str(&id)
-                        .map_err(|_| TapleError::DigestIdentifierGenerationFailed)?,
-                )
+                Some(DigestIdentifier::from_str(&id).map_err(|_| {
+                    TapleError::DigestIdentifierGenerationFailed { id: id.to_owned() }
+                })?)
             } else {
                 None
             },
@@ -26,11 +26,17 @@ impl TryInto<Signature> for TapleSignature {
 
     fn try_into(self) -> Result<Signature, Self::Error> {
         Ok(Signature {
-            signer: KeyIdentifier::from_str(&self.signer)
-                .map_err(|_| TapleError::KeyIdentifierGenerationFailed)?,
+            signer: KeyIdentifier::from_str(&self.signer).map_err(|_| {
+                TapleError::KeyIdentifierGenerationFailed {
+                    id: self.signer.to_owned(),
+                }
+            })?,
             timestamp: TimeStamp(self.timestamp),
-            value: SignatureIdentifier::from_str(&self.value)
-                .map_err(|_| TapleError::SignatureIdentifierGenerationFailed)?,
+            value: SignatureIdentifier::from_str(&self.value).map_err(|_| {
+                TapleError::SignatureIdentifierGenerationFailed {
+                    id: self.value.to_owned(),
+                }
+            })?,
         })
     }
 }
@@ -0,0 +1,19 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubjectCreationStatus {
+    /// The creation request has been sent and is being processed
+    Requested,
+    /// The subject exists and its initial fact is being submitted
+    SubmittingInitialFact,
+    Created,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubjectCreationProgress {
+    pub request_id: String,
+    pub status: SubjectCreationStatus,
+    pub subject_id: Option<String>,
+    pub initial_fact_request_id: Option<String>,
+    /// Reason of the failure when `status` is `Failed`
+    pub error: Option<String>,
+}
@@ -1,18 +1,22 @@
 use std::sync::{Arc, RwLock};
 
 use serde::Deserialize;
-use taple_core::{Derivable, Api, SubjectData};
-use tokio::runtime::Runtime;
+use taple_core::{Api, Derivable, SubjectData};
+use tokio::runtime::Handle;
 
-use crate::TapleError;
+use crate::{api::run_on_runtime, verification::verify_validation_proof, TapleError};
 
-use super::{policy::Policy, role::Role, schema::Schema, user_subject::UserSubject};
+use super::{
+    others::ValidationProofAndSignatures, policy::Policy, role::Role, schema::Schema,
+    user_subject::UserSubject, verification::ValidationProofReport,
+};
 
 //Governance abstration to simplify usage for third parties
+#[derive(Clone)]
 pub struct UserGovernance {
     pub api: Api,
-    pub governance_data: RwLock<SubjectData>,
-    pub runtime: Arc<Runtime>,
+    pub governance_data: Arc<RwLock<SubjectData>>,
+    pub runtime: Handle,
 }
 
 //Implementar un constructor que verifique el schema ID para saber si puede ser governanza
@@ -23,45 +27,26 @@ impl UserGovernance {
                 if subject_data.schema_id == "governance" {
                     Ok(Self {
                         api: subject.api.clone(),
-                        governance_data: RwLock::new(subject_data),
+                        governance_data: Arc::new(RwLock::new(subject_data)),
                         runtime: subject.runtime.clone(),
                     })
                 } else {
-                    Err(TapleError::NotFound("Schema ID not valid".to_owned()))
+                    Err(TapleError::InvalidParameters {
+                        operation: "UserGovernance::new".to_owned(),
+                        message: format!("Schema ID not valid: {}", subject_data.schema_id),
+                    })
                 }
             }
-            None => Err(TapleError::NotFound("Subject data not found".to_owned())),
+            None => Err(TapleError::NotFound {
+                operation: "UserGovernance::new".to_owned(),
+                id: subject.get_subject_request(),
+                message: "Subject data not found".to_owned(),
+            }),
         }
     }
 
     pub fn refresh(&self) -> Result<(), TapleError> {
-        //Checks local SN and compares it to Taples version. If is underversioned it updates itself, if not it does nothing.
-        self.runtime.block_on(async {
-            let (subject_id, sn) = {
-                let Ok(lock) = self.governance_data.read() else {
-                    return Err(TapleError::LockIsPoisoned);
-                };
-                (lock.subject_id.clone(), lock.sn)
-            };
-            match self.api.get_subject(subject_id).await {
-                Ok(api_sid) => {
-                    if api_sid.sn == sn {
-                        //Estado actualizado
-                        Ok(())
-                    } else {
-                        //Actualizar estado
-                        if sn < api_sid.sn {
-                            let Ok(mut lock) = self.governance_data.write() else {
-                                return Err(TapleError::LockIsPoisoned);
-                            };
-                            *lock = api_sid;
-                        }
-                        Ok(())
-                    }
-                }
-                Err(error) => Err(TapleError::ExecutionError(error.to_string())),
-            }
-        })
+        self.runtime.block_on(self.refresh_async())
     }
 
     pub fn get_members(&self) -> Result<Vec<String>, TapleError> {
@@ -152,6 +137,19 @@ impl UserGovernance {
         Ok(schemas)
     }
 
+    pub fn verify_validation_proof(
+        &self,
+        proof_and_signatures: ValidationProofAndSignatures,
+    ) -> Result<ValidationProofReport, TapleError> {
+        let (properties, sn) = {
+            let Ok(lock) = self.governance_data.read() else {
+                return Err(TapleError::LockIsPoisoned);
+            };
+            (lock.properties.0.clone(), lock.sn)
+        };
+        verify_validation_proof(proof_and_signatures, &properties, sn)
+    }
+
     pub fn get_subject_id(&self) -> String {
         self.governance_data.read().unwrap().subject_id.to_str()
     }
@@ -197,3 +195,44 @@ impl UserGovernance {
             .to_string()
     }
 }
+
+// Async exports, executed on the node runtime
+#[uniffi::export(async_runtime = "tokio")]
+impl UserGovernance {
+    pub async fn refresh_async(&self) -> Result<(), TapleError> {
+        //Checks local SN and compares it to Taples version. If is underversioned it updates itself, if not it does nothing.
+        let governance = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let (subject_id, sn) = {
+                let Ok(lock) = governance.governance_data.read() else {
+                    return Err(TapleError::LockIsPoisoned);
+                };
+                (lock.subject_id.clone(), lock.sn)
+            };
+            let subject_id_str = subject_id.to_str();
+            match governance.api.get_subject(subject_id).await {
+                Ok(api_sid) => {
+                    if api_sid.sn == sn {
+                        //Estado actualizado
+                        Ok(())
+                    } else {
+                        //Actualizar estado
+                        if sn < api_sid.sn {
+                            let Ok(mut lock) = governance.governance_data.write() else {
+                                return Err(TapleError::LockIsPoisoned);
+                            };
+                            *lock = api_sid;
+                        }
+                        Ok(())
+                    }
+                }
+                Err(error) => Err(TapleError::from_api_error(
+                    "refresh",
+                    Some(&subject_id_str),
+                    error,
+                )),
+            }
+        })
+        .await
+    }
+}
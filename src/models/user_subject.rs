@@ -1,39 +1,48 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
 //Abstraccion del Sujeto de Taple para facilitar su uso por terceros
-use crate::{TapleError, TapleSignedEventRequest};
+use crate::{
+    api::{run_on_runtime, wait_for_request_completion},
+    TapleError, TapleSignedEventRequest,
+};
 use taple_core::{
-    crypto::{KeyPair},
-    request::{EOLRequest, FactRequest, RequestState},
+    crypto::KeyPair,
+    request::{EOLRequest, FactRequest, RequestState, TransferRequest},
     signature::{Signature, Signed},
-    Derivable, DigestIdentifier, EventRequest, Api,
-    SubjectData, ValueWrapper,
+    Api, Derivable, DigestIdentifier, EventRequest, KeyIdentifier, SubjectData, ValueWrapper,
 };
-use tokio::runtime::Runtime;
+use tokio::{runtime::Handle, sync::broadcast};
 
 use super::user_governance::UserGovernance;
 
+#[derive(Clone)]
 pub struct UserSubject {
     pub api: Api,
     keys: KeyPair,
-    pub runtime: Arc<Runtime>,
-    pub subject_data: RwLock<Option<SubjectData>>,
+    pub runtime: Handle,
+    pub subject_data: Arc<RwLock<Option<SubjectData>>>,
     subject_request: Option<DigestIdentifier>,
+    notification_signal: broadcast::Sender<()>,
 }
 
 pub fn create_subject(
     api: Api,
     keys: KeyPair,
-    runtime: Arc<Runtime>,
+    runtime: Handle,
     subject_data: RwLock<Option<SubjectData>>,
     subject_request: Option<DigestIdentifier>,
+    notification_signal: broadcast::Sender<()>,
 ) -> UserSubject {
     UserSubject {
         api,
         keys,
         runtime,
-        subject_data,
+        subject_data: Arc::new(subject_data),
         subject_request: subject_request,
+        notification_signal,
     }
 }
 
@@ -49,153 +58,38 @@ impl UserSubject {
     }
 
     pub fn end_life_cicle(&self) -> Result<String, TapleError> {
-        let subject_id = self._get_subject_id()?;
-
-        let eol_event = EventRequest::EOL(EOLRequest {
-            subject_id: subject_id,
-        });
-
-        let signed_event = self.event_signing(eol_event);
-
-        match signed_event {
-            Ok(se) => self.runtime.block_on(async {
-                let req_id = self
-                    .api
-                    .external_request(se)
-                    .await
-                    .map_err(|e| TapleError::ExecutionError(e.to_string()))?;
-
-                let id = self
-                    .api
-                    .get_request(req_id)
-                    .await
-                    .map_err(|e| TapleError::ExecutionError(e.to_string()))?;
-                Ok(id.id.to_str())
-            }),
-            Err(e) => Err(e),
-        }
+        self.runtime.block_on(self.end_life_cicle_async())
     }
 
     pub fn new_fact_event(&self, payload: String) -> Result<String, TapleError> {
-        let subject_id = self._get_subject_id()?;
-
-        let fact_event = EventRequest::Fact(FactRequest {
-            subject_id: subject_id,
-            payload: ValueWrapper(serde_json::from_str(&payload).unwrap()),
-        });
-
-        let signed_event = self.event_signing(fact_event);
+        self.runtime.block_on(self.new_fact_event_async(payload))
+    }
 
-        match signed_event {
-            Ok(se) => self.runtime.block_on(async {
-                let req = self
-                    .api
-                    .external_request(se)
-                    .await
-                    .map_err(|e| TapleError::ExecutionError(e.to_string()))?;
-                Ok(req.to_str())
-            }),
-            Err(e) => Err(e),
-        }
+    pub fn transfer(
+        &self,
+        new_owner_public_key: String,
+        timeout_ms: u64,
+    ) -> Result<String, TapleError> {
+        self.runtime
+            .block_on(self.transfer_async(new_owner_public_key, timeout_ms))
     }
 
     pub fn refresh(&self) -> Result<(), TapleError> {
-        //Checks local SN and compares it to Taples version. If is underversioned it updates itself, if not it does nothing.
-        //Comprovar event request y si esta completada recuperar sujeto
-        let subject_d = {
-            match self.subject_data.read() {
-                Ok(sd_pointer) => sd_pointer.clone(),
-                Err(_) => return Err(TapleError::LockIsPoisoned),
-            }
-        };
-        match subject_d {
-            Some(subject_data) => {
-                self.runtime.block_on(async {
-                    let sn = subject_data.sn;
-                    match self.api.get_subject(subject_data.subject_id.clone()).await {
-                        Ok(api_sid) => {
-                            if api_sid.sn == sn {
-                                //Estado actualizado
-                                Ok(())
-                            } else {
-                                //Actualizar estado
-                                if sn < api_sid.sn {
-                                    let Ok(mut lock) = self.subject_data.write() else {
-                                                return Err(TapleError::LockIsPoisoned);
-                                            };
-                                    *lock = Some(api_sid);
-                                }
-                                Ok(())
-                            }
-                        }
-                        Err(error) => Err(TapleError::ExecutionError(error.to_string())),
-                    }
-                })
-            }
-            None => {
-                match &self.subject_request {
-                    Some(request) => {
-                        self.runtime.block_on(async {
-                            match self.api.get_request(request.clone()).await {
-                                Ok(event) => {
-                                    if event.state != RequestState::Finished {
-                                        return Ok(());
-                                    }
-                                    match event.subject_id {
-                                        Some(sid) => {
-                                            let subject =
-                                                self.api.get_subject(sid).await.map_err(|e| {
-                                                    TapleError::ExecutionError(e.to_string())
-                                                })?;
-
-                                            match self.subject_data.write() {
-                                                Ok(mut lock) => {
-                                                    *lock = Some(subject);
-                                                    return Ok(());
-                                                }
-                                                Err(_) => return Err(TapleError::LockIsPoisoned),
-                                            }
-                                        }
-                                        None => {
-                                            return Err(TapleError::NotFound(
-                                                "Subject ID not found".to_owned(),
-                                            ))
-                                        }
-                                    }
-                                }
-                                Err(e) => return Err(TapleError::ExecutionError(e.to_string())),
-                            }
-                        })
-                    }
-                    None => return Err(TapleError::NotFound("Event request not found".to_owned())),
-                }
-            }
-        }
+        self.runtime.block_on(self.refresh_async())
     }
 
     pub fn external_invokation(
         &self,
         event: TapleSignedEventRequest,
     ) -> Result<String, TapleError> {
-        let data: Signed<EventRequest> = event
-            .try_into()
-            .map_err(|e: TapleError| TapleError::ExecutionError(e.to_string()))?;
-
-        self.runtime.block_on(async {
-            let res = self
-                .api
-                .external_request(data)
-                .await
-                .map_err(|e| TapleError::ExecutionError(e.to_string()))?;
-            Ok(res.to_str())
-        })
+        self.runtime.block_on(self.external_invokation_async(event))
     }
 
     pub fn to_governance(&self) -> Option<Arc<UserGovernance>> {
         match self.subject_data.read().unwrap().clone() {
             Some(subject_data) => Some(Arc::new(UserGovernance {
                 api: self.api.clone(),
-                governance_data: RwLock::new(subject_data),
+                governance_data: Arc::new(RwLock::new(subject_data)),
                 runtime: self.runtime.clone(),
             })),
             None => None,
@@ -204,47 +98,65 @@ impl UserSubject {
 
     //Getter of subjectData fields
     pub fn get_subject_id(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.subject_id.to_str())
     }
 
     pub fn get_governance_id(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.governance_id.to_str())
     }
 
     pub fn get_sn(&self) -> Option<u64> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.sn)
     }
 
     pub fn get_public_key(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.public_key.to_str())
     }
 
     pub fn get_namespace(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.namespace.clone())
     }
 
     pub fn get_schema_id(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.schema_id.clone())
     }
 
     pub fn get_owner(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.owner.to_str())
     }
 
     pub fn get_creator(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.creator.to_str())
     }
 
     pub fn get_properties(&self) -> Option<String> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.properties.0.to_string())
     }
 
@@ -256,7 +168,9 @@ impl UserSubject {
     }
 
     pub fn get_is_active(&self) -> Option<bool> {
-        let Some(lock) = &*self.subject_data.read().unwrap() else { return None };
+        let Some(lock) = &*self.subject_data.read().unwrap() else {
+            return None;
+        };
         Some(lock.active)
     }
 
@@ -268,7 +182,253 @@ impl UserSubject {
                 content: event,
                 signature,
             }),
-            Err(e) => Err(TapleError::SignatureGenerationFailed(e.to_string())),
+            Err(e) => Err(TapleError::SignatureGenerationFailed {
+                message: e.to_string(),
+            }),
+        }
+    }
+}
+
+// Async exports, executed on the node runtime
+#[uniffi::export(async_runtime = "tokio")]
+impl UserSubject {
+    pub async fn end_life_cicle_async(&self) -> Result<String, TapleError> {
+        let subject_id = self._get_subject_id()?;
+
+        let eol_event = EventRequest::EOL(EOLRequest {
+            subject_id: subject_id,
+        });
+
+        let signed_event = self.event_signing(eol_event)?;
+
+        let subject = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let req_id = subject
+                .api
+                .external_request(signed_event)
+                .await
+                .map_err(|e| TapleError::from_api_error("end_life_cicle", None, e))?;
+
+            let id = subject
+                .api
+                .get_request(req_id)
+                .await
+                .map_err(|e| TapleError::from_api_error("end_life_cicle", None, e))?;
+            Ok(id.id.to_str())
+        })
+        .await
+    }
+
+    pub async fn new_fact_event_async(&self, payload: String) -> Result<String, TapleError> {
+        let subject_id = self._get_subject_id()?;
+
+        let fact_event = EventRequest::Fact(FactRequest {
+            subject_id: subject_id,
+            payload: ValueWrapper(
+                serde_json::from_str(&payload).map_err(|_| TapleError::NoJSONString)?,
+            ),
+        });
+
+        let signed_event = self.event_signing(fact_event)?;
+
+        let subject = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let req = subject
+                .api
+                .external_request(signed_event)
+                .await
+                .map_err(|e| TapleError::from_api_error("new_fact_event", None, e))?;
+            Ok(req.to_str())
+        })
+        .await
+    }
+
+    /// Transfers the ownership of the subject to `new_owner_public_key`. The call returns once
+    /// the request has been processed, with the cached subject data already updated, or fails
+    /// with `RequestTimeout` if it takes longer than `timeout_ms`.
+    pub async fn transfer_async(
+        &self,
+        new_owner_public_key: String,
+        timeout_ms: u64,
+    ) -> Result<String, TapleError> {
+        let public_key = KeyIdentifier::from_str(&new_owner_public_key).map_err(|_| {
+            TapleError::KeyIdentifierGenerationFailed {
+                id: new_owner_public_key.to_owned(),
+            }
+        })?;
+        let subject_id = self._get_subject_id()?;
+        if self.get_is_active() != Some(true) {
+            return Err(TapleError::SubjectInactive {
+                operation: "transfer".to_owned(),
+                id: subject_id.to_str(),
+            });
+        }
+
+        let transfer_event = EventRequest::Transfer(TransferRequest {
+            subject_id: subject_id.clone(),
+            public_key,
+        });
+
+        let signed_event = self.event_signing(transfer_event)?;
+
+        let subject = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let req_id = subject
+                .api
+                .external_request(signed_event)
+                .await
+                .map_err(|e| {
+                    TapleError::from_api_error("transfer", Some(&subject_id.to_str()), e)
+                })?;
+
+            let request = wait_for_request_completion(
+                &subject.api,
+                &subject.notification_signal,
+                req_id.clone(),
+                timeout_ms,
+            )
+            .await?;
+            if request.state != RequestState::Finished || request.success != Some(true) {
+                return Err(TapleError::EventCreationFailed {
+                    operation: "transfer".to_owned(),
+                    message: format!("Transfer request {} was not successful", req_id.to_str()),
+                });
+            }
+
+            let api_subject = subject
+                .api
+                .get_subject(subject_id.clone())
+                .await
+                .map_err(|e| {
+                    TapleError::from_api_error("transfer", Some(&subject_id.to_str()), e)
+                })?;
+            let Ok(mut lock) = subject.subject_data.write() else {
+                return Err(TapleError::LockIsPoisoned);
+            };
+            *lock = Some(api_subject);
+            Ok(req_id.to_str())
+        })
+        .await
+    }
+
+    pub async fn refresh_async(&self) -> Result<(), TapleError> {
+        //Checks local SN and compares it to Taples version. If is underversioned it updates itself, if not it does nothing.
+        //Comprovar event request y si esta completada recuperar sujeto
+        let subject_d = {
+            match self.subject_data.read() {
+                Ok(sd_pointer) => sd_pointer.clone(),
+                Err(_) => return Err(TapleError::LockIsPoisoned),
+            }
+        };
+        let subject = self.clone();
+        match subject_d {
+            Some(subject_data) => {
+                run_on_runtime(&self.runtime, async move {
+                    let sn = subject_data.sn;
+                    match subject
+                        .api
+                        .get_subject(subject_data.subject_id.clone())
+                        .await
+                    {
+                        Ok(api_sid) => {
+                            if api_sid.sn == sn {
+                                //Estado actualizado
+                                Ok(())
+                            } else {
+                                //Actualizar estado
+                                if sn < api_sid.sn {
+                                    let Ok(mut lock) = subject.subject_data.write() else {
+                                        return Err(TapleError::LockIsPoisoned);
+                                    };
+                                    *lock = Some(api_sid);
+                                }
+                                Ok(())
+                            }
+                        }
+                        Err(error) => Err(TapleError::from_api_error(
+                            "refresh",
+                            Some(&subject_data.subject_id.to_str()),
+                            error,
+                        )),
+                    }
+                })
+                .await
+            }
+            None => match self.subject_request.clone() {
+                Some(request) => {
+                    run_on_runtime(&self.runtime, async move {
+                        match subject.api.get_request(request.clone()).await {
+                            Ok(event) => {
+                                if event.state != RequestState::Finished {
+                                    return Ok(());
+                                }
+                                match event.subject_id {
+                                    Some(sid) => {
+                                        let api_subject =
+                                            subject.api.get_subject(sid.clone()).await.map_err(
+                                                |e| {
+                                                    TapleError::from_api_error(
+                                                        "refresh",
+                                                        Some(&sid.to_str()),
+                                                        e,
+                                                    )
+                                                },
+                                            )?;
+
+                                        match subject.subject_data.write() {
+                                            Ok(mut lock) => {
+                                                *lock = Some(api_subject);
+                                                return Ok(());
+                                            }
+                                            Err(_) => return Err(TapleError::LockIsPoisoned),
+                                        }
+                                    }
+                                    None => {
+                                        return Err(TapleError::NotFound {
+                                            operation: "refresh".to_owned(),
+                                            id: Some(request.to_str()),
+                                            message: "Subject ID not found".to_owned(),
+                                        })
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                return Err(TapleError::from_api_error(
+                                    "refresh",
+                                    Some(&request.to_str()),
+                                    e,
+                                ))
+                            }
+                        }
+                    })
+                    .await
+                }
+                None => {
+                    return Err(TapleError::NotFound {
+                        operation: "refresh".to_owned(),
+                        id: None,
+                        message: "Event request not found".to_owned(),
+                    })
+                }
+            },
         }
     }
+
+    pub async fn external_invokation_async(
+        &self,
+        event: TapleSignedEventRequest,
+    ) -> Result<String, TapleError> {
+        let data: Signed<EventRequest> = event.try_into()?;
+
+        let subject = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            let res = subject
+                .api
+                .external_request(data)
+                .await
+                .map_err(|e| TapleError::from_api_error("external_invokation", None, e))?;
+            Ok(res.to_str())
+        })
+        .await
+    }
 }
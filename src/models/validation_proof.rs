@@ -1,4 +1,10 @@
-use taple_core::{ValidationProof as TapleValidationProof, Derivable};
+use std::str::FromStr;
+
+use taple_core::{
+    Derivable, DigestIdentifier, KeyIdentifier, ValidationProof as TapleValidationProof,
+};
+
+use crate::TapleError;
 
 pub struct ValidationProof {
     pub subject_id: String,
@@ -31,3 +37,42 @@ impl From<TapleValidationProof> for ValidationProof {
         }
     }
 }
+
+impl TryInto<TapleValidationProof> for ValidationProof {
+    type Error = TapleError;
+    fn try_into(self) -> Result<TapleValidationProof, Self::Error> {
+        Ok(TapleValidationProof {
+            subject_id: DigestIdentifier::from_str(&self.subject_id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.subject_id.to_owned(),
+                }
+            })?,
+            schema_id: self.schema_id,
+            namespace: self.namespace,
+            name: self.name,
+            subject_public_key: KeyIdentifier::from_str(&self.subject_public_key).map_err(
+                |_| TapleError::KeyIdentifierGenerationFailed {
+                    id: self.subject_public_key.to_owned(),
+                },
+            )?,
+            governance_id: DigestIdentifier::from_str(&self.governance_id).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.governance_id.to_owned(),
+                }
+            })?,
+            genesis_governance_version: self.genesis_governance_version,
+            sn: self.sn,
+            prev_event_hash: DigestIdentifier::from_str(&self.prev_event_hash).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.prev_event_hash.to_owned(),
+                }
+            })?,
+            event_hash: DigestIdentifier::from_str(&self.event_hash).map_err(|_| {
+                TapleError::DigestIdentifierGenerationFailed {
+                    id: self.event_hash.to_owned(),
+                }
+            })?,
+            governance_version: self.governance_version,
+        })
+    }
+}
@@ -0,0 +1,38 @@
+#[derive(Clone, Debug)]
+pub enum ChainBreakReason {
+    /// The event belongs to another subject
+    SubjectMismatch,
+    /// The event sn does not follow the previous one
+    SequenceGap,
+    /// `hash_prev_event` does not match the hash of the previous event, or is not empty in the
+    /// genesis event
+    PreviousHashMismatch,
+    /// The event signature doesn't match its content
+    InvalidEventSignature,
+    /// The signature of the event request doesn't match its content
+    InvalidRequestSignature,
+    /// The hash of the event could not be computed
+    HashComputationFailed,
+    /// The ledger ends before the sn reported by the subject
+    MissingEvents,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChainVerificationReport {
+    pub subject_id: String,
+    pub events_checked: u64,
+    pub valid: bool,
+    pub first_broken_sn: Option<u64>,
+    pub reason: Option<ChainBreakReason>,
+    pub detail: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationProofReport {
+    pub valid: bool,
+    pub governance_version_matches: bool,
+    pub quorum_required: u64,
+    pub accepted_signers: Vec<String>,
+    pub invalid_signatures: Vec<String>,
+    pub non_validator_signers: Vec<String>,
+}
@@ -1,15 +1,28 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock, Weak,
+};
 
-use taple_core::{crypto::KeyPair, Api, Node};
-use tokio::runtime::Runtime;
+use taple_core::{
+    crypto::{KeyMaterial, KeyPair},
+    Api, Derivable, KeyIdentifier, Node,
+};
+use tokio::{
+    runtime::Runtime,
+    sync::{broadcast, oneshot},
+};
 
 use crate::{
     api::{create_taple_api, TapleAPI},
-    db::{WrapperCollection, WrapperManager},
+    backup,
+    db::{DatabaseCacheStats, NodeDatabase, WrapperCollection, WrapperManager},
+    keys, logger,
+    models::identity::TapleIdentity,
     notification::TapleNotification,
+    registry,
     shutdown::ShutdownSignal,
-    subject_builder::SubjectBuilder,
-    NotificationError, TapleError,
+    subject_builder::{create_subject_builder, SubjectBuilder},
+    BackupError, NotificationError, TapleError,
 };
 
 pub trait NotificationHandlerInterface {
@@ -22,33 +35,98 @@ pub struct TapleNode {
     keypair: KeyPair,
     taple: RwLock<Option<Node<WrapperManager, WrapperCollection>>>,
     runtime: Arc<Runtime>,
+    notification_signal: broadcast::Sender<()>,
+    /// Keeps the current-thread runtime driver alive. Dropping it stops the driver thread
+    _runtime_driver: Option<oneshot::Sender<()>>,
+    controller_id: String,
+    /// Runtime thread name registered in the logger for the node log tag
+    log_thread_name: Option<String>,
+    database: NodeDatabase,
+    /// Handed to the shutdown signals, which release the node once the shutdown is requested
+    weak_self: Weak<TapleNode>,
+    torn_down: AtomicBool,
 }
 
 impl TapleNode {
-    pub fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
         shutdown_sender: tokio::sync::mpsc::Sender<()>,
         api: Api,
         keypair: KeyPair,
         taple: RwLock<Option<Node<WrapperManager, WrapperCollection>>>,
         runtime: Arc<Runtime>,
+        runtime_driver: Option<oneshot::Sender<()>>,
+        controller_id: String,
+        log_thread_name: Option<String>,
+        database: NodeDatabase,
+        weak_self: Weak<TapleNode>,
     ) -> Self {
+        // Every notification drained from the node wakes up the requests being awaited
+        let (notification_signal, _) = broadcast::channel(16);
         Self {
             shutdown_sender,
             api,
             keypair,
             taple,
             runtime,
+            notification_signal,
+            _runtime_driver: runtime_driver,
+            controller_id,
+            log_thread_name,
+            database,
+            weak_self,
+            torn_down: AtomicBool::new(false),
+        }
+    }
+
+    /// Releases the process-wide resources held by the node. Only the first call, on shutdown
+    /// or when the node is dropped, has an effect
+    pub(crate) fn teardown(&self) {
+        if self.torn_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        registry::unregister(&self.controller_id, self);
+        if let Some(thread_name) = &self.log_thread_name {
+            logger::unregister_thread_tag(thread_name, &self.controller_id);
         }
     }
 
     pub fn get_api(&self) -> Arc<TapleAPI> {
         Arc::new(create_taple_api(
             self.api.clone(),
-            self.runtime.clone(),
+            self.runtime.handle().clone(),
             self.keypair.clone(),
+            self.notification_signal.clone(),
+            self.database.iterator_errors.clone(),
         ))
     }
 
+    /// Number of database iterations that failed since the node started. Each failure is also
+    /// logged with the collection it happened on
+    pub fn get_database_iterator_errors(&self) -> u64 {
+        self.database.iterator_errors.count()
+    }
+
+    /// Hits and misses of the database cache since the node started
+    pub fn get_database_cache_stats(&self) -> DatabaseCacheStats {
+        self.database.cache.stats()
+    }
+
+    /// Archives the node database, encrypted when a passphrase is given. Writes made by the
+    /// node while exporting may be partially included, so it should be idle
+    pub fn export_backup(&self, passphrase: Option<String>) -> Result<Vec<u8>, BackupError> {
+        backup::export_backup(&self.database, passphrase.as_deref())
+    }
+
+    pub fn export_backup_to_file(
+        &self,
+        path: String,
+        passphrase: Option<String>,
+    ) -> Result<(), BackupError> {
+        let archive = self.export_backup(passphrase)?;
+        std::fs::write(path, archive).map_err(|e| BackupError::IoError(e.to_string()))
+    }
+
     pub fn receive_blocking(&self) -> Result<TapleNotification, NotificationError> {
         self.runtime.block_on(async {
             let mut write_lock = self
@@ -64,6 +142,7 @@ impl TapleNode {
                 .recv_notification()
                 .await
                 .ok_or(NotificationError::NoConnection)?;
+            let _ = self.notification_signal.send(());
             Ok(notification.into())
         })
     }
@@ -72,12 +151,18 @@ impl TapleNode {
         let node = {
             let mut write_lock = self.taple.write().map_err(|_| TapleError::LockIsPoisoned)?;
             if write_lock.is_none() {
-                return Err(TapleError::NodeUnavailable);
+                return Err(TapleError::NodeUnavailable {
+                    operation: "drop_notifications".to_owned(),
+                });
             }
             write_lock.take().unwrap()
         };
+        let notification_signal = self.notification_signal.clone();
         self.runtime.block_on(async {
-            node.handle_notifications(|_| {}).await;
+            node.handle_notifications(|_| {
+                let _ = notification_signal.send(());
+            })
+            .await;
         });
         Ok(())
     }
@@ -89,12 +174,16 @@ impl TapleNode {
         let node = {
             let mut write_lock = self.taple.write().map_err(|_| TapleError::LockIsPoisoned)?;
             if write_lock.is_none() {
-                return Err(TapleError::NodeUnavailable);
+                return Err(TapleError::NodeUnavailable {
+                    operation: "handle_notifications".to_owned(),
+                });
             }
             write_lock.take().unwrap()
         };
+        let notification_signal = self.notification_signal.clone();
         self.runtime.block_on(async {
             node.handle_notifications(|notification| {
+                let _ = notification_signal.send(());
                 handler.process_notification(TapleNotification::from(notification));
             })
             .await;
@@ -102,10 +191,26 @@ impl TapleNode {
         Ok(())
     }
 
+    pub fn get_identity(&self) -> Result<TapleIdentity, TapleError> {
+        let public_key = self.keypair.public_key_bytes();
+        let controller_id = KeyIdentifier::new(self.keypair.get_key_derivator(), &public_key);
+        let peer_id = keys::peer_id(&self.keypair).map_err(|e| TapleError::ExecutionError {
+            operation: "get_identity".to_owned(),
+            message: e.to_string(),
+        })?;
+        Ok(TapleIdentity {
+            controller_id: controller_id.to_str(),
+            public_key,
+            key_derivator: self.keypair.get_key_derivator().into(),
+            peer_id,
+        })
+    }
+
     pub fn get_shutdown_handler(&self) -> Arc<ShutdownSignal> {
         Arc::new(ShutdownSignal {
             runtime: self.runtime.clone(),
             shutdown: RwLock::new(self.shutdown_sender.clone()),
+            node: self.weak_self.clone(),
         })
     }
 
@@ -113,26 +218,31 @@ impl TapleNode {
         let node = {
             let mut write_lock = self.taple.write().map_err(|_| TapleError::LockIsPoisoned)?;
             if write_lock.is_none() {
-                return Err(TapleError::NodeUnavailable);
+                return Err(TapleError::NodeUnavailable {
+                    operation: "shutdown_gracefully".to_owned(),
+                });
             }
             write_lock.take().unwrap()
         };
         self.runtime.block_on(async {
             node.shutdown_gracefully().await;
         });
+        self.teardown();
         Ok(())
     }
 
     pub fn get_subject_builder(&self) -> Arc<SubjectBuilder> {
-        let sb_api = self.get_api().api.clone();
-        let sb_runtime = self.runtime.clone();
-        let sb_keys = self.keypair.clone();
-        Arc::new(SubjectBuilder {
-            api: sb_api,
-            runtime: sb_runtime,
-            keys: sb_keys,
-            name: RwLock::new(None),
-            namespace: RwLock::new(None),
-        })
+        Arc::new(create_subject_builder(
+            self.api.clone(),
+            self.runtime.handle().clone(),
+            self.keypair.clone(),
+            self.notification_signal.clone(),
+        ))
+    }
+}
+
+impl Drop for TapleNode {
+    fn drop(&mut self) {
+        self.teardown();
     }
 }
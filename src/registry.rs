@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::{InitializationError, TapleNode};
+
+/// Nodes running in this process, by controller id
+static NODES: Mutex<Vec<(String, Weak<TapleNode>)>> = Mutex::new(Vec::new());
+
+pub(crate) fn is_running(controller_id: &str) -> bool {
+    get_node(controller_id.to_owned()).is_some()
+}
+
+pub(crate) fn register(
+    controller_id: &str,
+    node: &Arc<TapleNode>,
+) -> Result<(), InitializationError> {
+    let mut nodes = NODES
+        .lock()
+        .map_err(|_| InitializationError::InternalError)?;
+    nodes.retain(|(_, node)| node.strong_count() > 0);
+    if nodes.iter().any(|(id, _)| id == controller_id) {
+        return Err(InitializationError::NodeAlreadyRunning(
+            controller_id.to_owned(),
+        ));
+    }
+    nodes.push((controller_id.to_owned(), Arc::downgrade(node)));
+    Ok(())
+}
+
+/// Removes the entry of `node`. A newer node started with the same controller id is kept
+pub(crate) fn unregister(controller_id: &str, node: *const TapleNode) {
+    if let Ok(mut nodes) = NODES.lock() {
+        nodes.retain(|(id, registered)| {
+            registered.strong_count() > 0 && !(id == controller_id && registered.as_ptr() == node)
+        });
+    }
+}
+
+/// Running node with the given controller id, if any
+pub fn get_node(controller_id: String) -> Option<Arc<TapleNode>> {
+    let nodes = NODES.lock().ok()?;
+    nodes
+        .iter()
+        .filter(|(id, _)| *id == controller_id)
+        .find_map(|(_, node)| node.upgrade())
+}
+
+/// Controller ids of the nodes running in this process
+pub fn get_running_nodes() -> Vec<String> {
+    let Ok(nodes) = NODES.lock() else {
+        return Vec::new();
+    };
+    nodes
+        .iter()
+        .filter(|(_, node)| node.strong_count() > 0)
+        .map(|(id, _)| id.clone())
+        .collect()
+}
@@ -1,14 +1,122 @@
-use taple_core::{
-    ListenAddr, NetworkSettings, NodeSettings, Settings as CoreSettings,
-};
+use std::sync::{Arc, Mutex, Weak};
+
+use taple_core::{ListenAddr, NetworkSettings, NodeSettings, Settings as CoreSettings};
+use tokio::runtime::{Builder, Runtime};
 
-use crate::{error::SettingsError, models::others::TapleKeyDerivator};
+use crate::{
+    error::{InitializationError, SettingsError},
+    models::others::{TapleDigestDerivator, TapleKeyDerivator, TaplePassvotation},
+};
 
 pub struct TapleSettings {
     pub listen_addr: Vec<String>,
     pub key_derivator: TapleKeyDerivator,
     pub private_key: Vec<u8>,
     pub known_nodes: Vec<String>,
+    /// Addresses announced to other nodes, for nodes behind NAT. Empty by default
+    pub external_address: Option<Vec<String>>,
+    /// Defaults to `Blake3_256`
+    pub digest_derivator: Option<TapleDigestDerivator>,
+    /// Fraction of the witnesses each message is sent to, in (0, 1]. Defaults to 0.25
+    pub replication_factor: Option<f64>,
+    /// Milliseconds between message retries. Must be greater than 0. Defaults to 3000
+    pub timeout: Option<u32>,
+    /// Defaults to `Disabled`
+    pub passvotation: Option<TaplePassvotation>,
+    /// Worker threads of the multi-thread runtime. Defaults to one per core
+    pub worker_threads: Option<u32>,
+    /// Name of the runtime threads. Defaults to `tokio-runtime-worker`
+    pub thread_name: Option<String>,
+    /// Stack size in bytes of the runtime threads. Defaults to the tokio default (2 MiB)
+    pub thread_stack_size: Option<u64>,
+    /// Drive the node from a single thread instead of a thread pool. Defaults to false
+    pub current_thread: Option<bool>,
+    /// Run the node on a runtime shared with the other nodes started with this option. The
+    /// runtime options of the first of them are used. Defaults to false
+    pub shared_runtime: Option<bool>,
+    /// Tag added to the log records emitted by the node runtime. Records can't be attributed
+    /// to a node when the runtime is shared, and the runtime thread name can't be used by
+    /// another running node with a tag
+    pub log_tag: Option<String>,
+    /// 32-byte key used to encrypt the values written to the database. A database must always
+    /// be opened with the same key; existing plaintext databases can't be encrypted in place
+    pub database_key: Option<Vec<u8>>,
+    /// Store the database keys as MACs too. Requires `database_key`. Defaults to false.
+    /// MACs don't keep the key order, so every iteration reads and decrypts all the entries
+    /// under the whole elements of its prefix and sorts the matching ones in memory before
+    /// returning the first one. Listings of large collections take time and memory linear in
+    /// their size instead of the page requested
+    pub hash_database_keys: Option<bool>,
+    /// Entries of each collection kept in memory to save database reads. Defaults to 0, which
+    /// disables the cache
+    pub database_cache_size: Option<u32>,
+}
+
+/// Runtime shared by the nodes started with `shared_runtime`. It lives while any of them does
+static SHARED_RUNTIME: Mutex<Option<Weak<Runtime>>> = Mutex::new(None);
+
+impl TapleSettings {
+    pub(crate) fn is_current_thread(&self) -> bool {
+        self.current_thread.unwrap_or(false)
+    }
+
+    pub(crate) fn is_shared_runtime(&self) -> bool {
+        self.shared_runtime.unwrap_or(false)
+    }
+
+    /// Name of the runtime threads, used to attribute log records to the node
+    pub(crate) fn runtime_thread_name(&self) -> Option<String> {
+        match (&self.thread_name, &self.log_tag) {
+            (Some(thread_name), _) => Some(thread_name.clone()),
+            (None, Some(log_tag)) => Some(format!("taple-{}", log_tag)),
+            (None, None) => None,
+        }
+    }
+
+    pub(crate) fn runtime(&self) -> Result<Arc<Runtime>, InitializationError> {
+        if !self.is_shared_runtime() {
+            return Ok(Arc::new(self.build_runtime()?));
+        }
+        if self.is_current_thread() {
+            return Err(InitializationError::InvalidSettings(
+                "A shared runtime can't be a current-thread runtime".to_owned(),
+            ));
+        }
+        let mut shared_runtime = SHARED_RUNTIME
+            .lock()
+            .map_err(|_| InitializationError::InternalError)?;
+        if let Some(runtime) = shared_runtime.as_ref().and_then(Weak::upgrade) {
+            return Ok(runtime);
+        }
+        let runtime = Arc::new(self.build_runtime()?);
+        *shared_runtime = Some(Arc::downgrade(&runtime));
+        Ok(runtime)
+    }
+
+    fn build_runtime(&self) -> Result<Runtime, InitializationError> {
+        let mut builder = if self.is_current_thread() {
+            Builder::new_current_thread()
+        } else {
+            let mut builder = Builder::new_multi_thread();
+            if let Some(worker_threads) = self.worker_threads {
+                if worker_threads == 0 {
+                    return Err(SettingsError::InvalidWorkerThreads.into());
+                }
+                builder.worker_threads(worker_threads as usize);
+            }
+            builder
+        };
+        if let Some(thread_name) = self.runtime_thread_name() {
+            builder.thread_name(thread_name);
+        }
+        if let Some(thread_stack_size) = self.thread_stack_size {
+            builder.thread_stack_size(thread_stack_size as usize);
+        }
+        builder
+            .enable_all()
+            .build()
+            .map_err(|e| InitializationError::RuntimeCreationFailed(e.to_string()))
+    }
 }
 
 impl TryInto<CoreSettings> for TapleSettings {
@@ -21,19 +129,40 @@ impl TryInto<CoreSettings> for TapleSettings {
             listen_addr
                 .push(ListenAddr::try_from(addr).map_err(|_| SettingsError::InvalidListenAddr)?);
         }
+        let external_address = self.external_address.unwrap_or_default();
+        for addr in external_address.iter() {
+            ListenAddr::try_from(addr.clone())
+                .map_err(|_| SettingsError::InvalidExternalAddress)?;
+        }
+        let replication_factor = self
+            .replication_factor
+            .unwrap_or(default_settings.node.replication_factor);
+        if !(replication_factor > 0.0 && replication_factor <= 1.0) {
+            return Err(SettingsError::InvalidReplicationFactor);
+        }
+        let timeout = self.timeout.unwrap_or(default_settings.node.timeout);
+        if timeout == 0 {
+            return Err(SettingsError::InvalidTimeout);
+        }
         Ok(CoreSettings {
             network: NetworkSettings {
                 listen_addr,
                 known_nodes: self.known_nodes,
-                external_address: Vec::new(),
+                external_address,
             },
             node: NodeSettings {
                 key_derivator: self.key_derivator.into(),
                 secret_key: hex_private_key,
-                digest_derivator: default_settings.node.digest_derivator,
-                replication_factor: default_settings.node.replication_factor,
-                timeout: default_settings.node.timeout,
-                passvotation: default_settings.node.passvotation,
+                digest_derivator: self
+                    .digest_derivator
+                    .map(Into::into)
+                    .unwrap_or(default_settings.node.digest_derivator),
+                replication_factor,
+                timeout,
+                passvotation: self
+                    .passvotation
+                    .map(Into::into)
+                    .unwrap_or(default_settings.node.passvotation),
             },
         })
     }
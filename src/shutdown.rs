@@ -1,12 +1,14 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Weak};
 
 use tokio::runtime::Runtime;
 
-use crate::error::ShutdownError;
+use crate::{error::ShutdownError, TapleNode};
 
 pub struct ShutdownSignal {
     pub runtime: Arc<Runtime>,
     pub shutdown: RwLock<tokio::sync::mpsc::Sender<()>>,
+    /// Removed from the node registry and the logger once the shutdown is sent
+    pub(crate) node: Weak<TapleNode>,
 }
 
 impl ShutdownSignal {
@@ -18,6 +20,9 @@ impl ShutdownSignal {
         self.runtime.block_on(async {
             let _ = write_lock.send(()).await;
         });
+        if let Some(node) = self.node.upgrade() {
+            node.teardown();
+        }
         Ok(())
     }
 }
@@ -1,137 +1,505 @@
 use std::{
+    future::Future,
     str::FromStr,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use taple_core::{
     crypto::KeyPair,
-    request::StartRequest,
+    request::{FactRequest, RequestState, StartRequest},
     signature::{Signature, Signed},
-    Api, DigestIdentifier, EventRequest, KeyDerivator,
+    Api, Derivable, DigestIdentifier, EventRequest, KeyIdentifier, SubjectData, ValueWrapper,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{broadcast, watch},
 };
-use tokio::runtime::Runtime;
 
 use crate::{
-    models::user_subject::{create_subject, UserSubject},
+    api::{run_on_runtime, wait_for_request_end},
+    models::{
+        others::TapleKeyDerivator,
+        subject_creation::{SubjectCreationProgress, SubjectCreationStatus},
+        user_subject::{create_subject, UserSubject},
+    },
     TapleError,
 };
 
+const BUILD_OPERATION: &str = "SubjectBuilder::build";
+
+#[derive(Clone, Default)]
+struct SubjectOptions {
+    name: Option<String>,
+    namespace: Option<String>,
+    key_derivator: Option<TapleKeyDerivator>,
+    public_key: Option<KeyIdentifier>,
+    initial_fact: Option<ValueWrapper>,
+}
+
 pub struct SubjectBuilder {
     pub api: Api,
-    pub runtime: Arc<Runtime>,
+    pub runtime: Handle,
     pub keys: KeyPair,
-    pub name: RwLock<Option<String>>,
-    pub namespace: RwLock<Option<String>>,
+    options: RwLock<SubjectOptions>,
+    pub notification_signal: broadcast::Sender<()>,
+}
+
+pub(crate) fn create_subject_builder(
+    api: Api,
+    runtime: Handle,
+    keys: KeyPair,
+    notification_signal: broadcast::Sender<()>,
+) -> SubjectBuilder {
+    SubjectBuilder {
+        api,
+        runtime,
+        keys,
+        options: RwLock::new(SubjectOptions::default()),
+        notification_signal,
+    }
+}
+
+fn invalid_parameters(message: &str) -> TapleError {
+    TapleError::InvalidParameters {
+        operation: BUILD_OPERATION.to_owned(),
+        message: message.to_owned(),
+    }
 }
 
 impl SubjectBuilder {
-    fn get_name(&self) -> Result<String, TapleError> {
-        match self.name.read() {
-            Ok(s) => match &*s {
-                Some(name) => Ok(name.to_string()),
-                None => Ok("".to_owned()),
-            },
-            Err(_) => Err(TapleError::LockIsPoisoned),
-        }
+    fn update_options(&self, update: impl FnOnce(&mut SubjectOptions)) -> Result<(), TapleError> {
+        let mut options = self
+            .options
+            .write()
+            .map_err(|_| TapleError::LockIsPoisoned)?;
+        update(&mut options);
+        Ok(())
     }
 
+    /// Required
     pub fn with_name(&self, name: String) -> Result<(), TapleError> {
-        let name_lock = self.name.write();
-        match name_lock {
-            Ok(mut nl) => {
-                *nl = Some(name);
-                Ok(())
-            }
-            Err(_) => Err(TapleError::LockIsPoisoned),
-        }
+        self.update_options(|options| options.name = Some(name))
     }
 
-    fn get_namespace(&self) -> Result<String, TapleError> {
-        match self.namespace.read() {
-            Ok(s) => match &*s {
-                Some(namespace) => Ok(namespace.to_string()),
-                None => Ok("".to_owned()),
-            },
-            Err(_) => Err(TapleError::LockIsPoisoned),
-        }
+    /// Defaults to the root namespace
+    pub fn with_namespace(&self, namespace: String) -> Result<(), TapleError> {
+        self.update_options(|options| options.namespace = Some(namespace))
     }
 
-    pub fn with_namespace(&self, namespace: String) -> Result<(), TapleError> {
-        let namespace_lock = self.namespace.write();
-        match namespace_lock {
-            Ok(mut nsl) => {
-                *nsl = Some(namespace);
-                Ok(())
+    /// Derivator of the subject keys generated by the node. Defaults to `Ed25519`
+    pub fn with_key_derivator(&self, key_derivator: TapleKeyDerivator) -> Result<(), TapleError> {
+        self.update_options(|options| options.key_derivator = Some(key_derivator))
+    }
+
+    /// Uses an existing public key for the subject instead of generating new keys
+    pub fn with_public_key(&self, public_key: String) -> Result<(), TapleError> {
+        let public_key = KeyIdentifier::from_str(&public_key).map_err(|_| {
+            TapleError::KeyIdentifierGenerationFailed {
+                id: public_key.to_owned(),
             }
-            Err(_) => Err(TapleError::LockIsPoisoned),
-        }
+        })?;
+        self.update_options(|options| options.public_key = Some(public_key))
+    }
+
+    /// JSON payload of a fact event submitted as soon as the subject is created
+    pub fn with_initial_fact(&self, payload: String) -> Result<(), TapleError> {
+        let payload: serde_json::Value =
+            serde_json::from_str(&payload).map_err(|_| TapleError::NoJSONString)?;
+        self.update_options(|options| options.initial_fact = Some(ValueWrapper(payload)))
+    }
+
+    /// Clears every option. Options are also cleared after each successful `build`
+    pub fn reset(&self) -> Result<(), TapleError> {
+        self.update_options(|options| *options = SubjectOptions::default())
     }
 
     pub fn build(
         &self,
         governance_id: String,
         schema_id: String,
-    ) -> Result<Arc<UserSubject>, TapleError> {
-        let subject_namespace: String = match self.get_namespace() {
-            Ok(e) => e,
-            Err(e) => return Err(e),
+    ) -> Result<Arc<SubjectCreation>, TapleError> {
+        self.runtime
+            .block_on(self.build_async(governance_id, schema_id))
+    }
+}
+
+// Async exports, executed on the node runtime
+#[uniffi::export(async_runtime = "tokio")]
+impl SubjectBuilder {
+    /// Sends the creation request and returns a handle to follow it. The creation itself
+    /// (and the initial fact, if any) is tracked in the background.
+    pub async fn build_async(
+        &self,
+        governance_id: String,
+        schema_id: String,
+    ) -> Result<Arc<SubjectCreation>, TapleError> {
+        let options = self
+            .options
+            .read()
+            .map_err(|_| TapleError::LockIsPoisoned)?
+            .clone();
+        let name = match options.name {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => return Err(invalid_parameters("Subject name is required")),
         };
-        let subject_name: String = match self.get_name() {
-            Ok(e) => e,
-            Err(e) => return Err(e),
+        if schema_id.trim().is_empty() {
+            return Err(invalid_parameters("Schema id is required"));
+        }
+        if options.public_key.is_some() && options.key_derivator.is_some() {
+            return Err(invalid_parameters(
+                "A public key and a key derivator can't be used together",
+            ));
+        }
+        let governance_id = DigestIdentifier::from_str(&governance_id).map_err(|_| {
+            TapleError::DigestIdentifierGenerationFailed {
+                id: governance_id.to_owned(),
+            }
+        })?;
+        let namespace = options.namespace.unwrap_or_default();
+        let derivator = options
+            .key_derivator
+            .unwrap_or(TapleKeyDerivator::Ed25519)
+            .into();
+        let initial_fact = options.initial_fact;
+        let public_key = options.public_key;
+
+        let api = self.api.clone();
+        let keys = self.keys.clone();
+        let runtime = self.runtime.clone();
+        let notification_signal = self.notification_signal.clone();
+        let creation = run_on_runtime(&self.runtime, async move {
+            let public_key = match public_key {
+                Some(public_key) => public_key,
+                None => api
+                    .add_keys(derivator)
+                    .await
+                    .map_err(|e| TapleError::from_api_error(BUILD_OPERATION, None, e))?,
+            };
+            let create_event = EventRequest::Create(StartRequest {
+                governance_id,
+                schema_id,
+                namespace,
+                name,
+                public_key,
+            });
+            let event_signed = sign_event(create_event, &keys)?;
+            let request_id = api
+                .external_request(event_signed)
+                .await
+                .map_err(|e| TapleError::from_api_error(BUILD_OPERATION, None, e))?;
+
+            let (progress_sender, progress_receiver) = watch::channel(CreationState {
+                progress: SubjectCreationProgress {
+                    request_id: request_id.to_str(),
+                    status: SubjectCreationStatus::Requested,
+                    subject_id: None,
+                    initial_fact_request_id: None,
+                    error: None,
+                },
+                subject_data: None,
+            });
+            tokio::spawn(track_creation(
+                api.clone(),
+                keys.clone(),
+                notification_signal.clone(),
+                request_id.clone(),
+                initial_fact,
+                progress_sender,
+            ));
+            Ok(SubjectCreation {
+                api,
+                keys,
+                runtime,
+                notification_signal,
+                request_id,
+                state: progress_receiver,
+            })
+        })
+        .await?;
+        // The options don't carry over to the next subject
+        self.reset()?;
+        Ok(Arc::new(creation))
+    }
+}
+
+fn sign_event(event: EventRequest, keys: &KeyPair) -> Result<Signed<EventRequest>, TapleError> {
+    match Signature::new(&event, keys) {
+        Ok(signature) => Ok(Signed {
+            content: event,
+            signature,
+        }),
+        Err(e) => Err(TapleError::SignatureGenerationFailed {
+            message: e.to_string(),
+        }),
+    }
+}
+
+#[derive(Clone)]
+struct CreationState {
+    progress: SubjectCreationProgress,
+    subject_data: Option<SubjectData>,
+}
+
+fn update_state(sender: &watch::Sender<CreationState>, update: impl FnOnce(&mut CreationState)) {
+    sender.send_modify(update);
+}
+
+fn fail_creation(sender: &watch::Sender<CreationState>, error: String) {
+    update_state(sender, |state| {
+        state.progress.status = SubjectCreationStatus::Failed;
+        state.progress.error = Some(error);
+    });
+}
+
+/// Runs `future` unless every `SubjectCreation` handle is dropped first, as nobody could read
+/// its result. With `keep_tracking` it runs to the end anyway
+async fn tracked<T>(
+    sender: &watch::Sender<CreationState>,
+    keep_tracking: bool,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    if keep_tracking {
+        return Some(future.await);
+    }
+    tokio::select! {
+        output = future => Some(output),
+        _ = sender.closed() => None,
+    }
+}
+
+/// Follows the creation request until it ends, however long it takes. The tracking only stops
+/// early when every handle is dropped and there is no initial fact left to submit
+async fn track_creation(
+    api: Api,
+    keys: KeyPair,
+    notification_signal: broadcast::Sender<()>,
+    request_id: DigestIdentifier,
+    initial_fact: Option<ValueWrapper>,
+    sender: watch::Sender<CreationState>,
+) {
+    let request = tracked(
+        &sender,
+        initial_fact.is_some(),
+        wait_for_request_end(&api, &notification_signal, &request_id),
+    )
+    .await;
+    let request = match request {
+        Some(Ok(request)) => request,
+        Some(Err(e)) => return fail_creation(&sender, e.to_string()),
+        None => return,
+    };
+    let subject_id = match request.subject_id {
+        Some(subject_id)
+            if request.state == RequestState::Finished && request.success == Some(true) =>
+        {
+            subject_id
+        }
+        _ => {
+            return fail_creation(
+                &sender,
+                format!(
+                    "Creation request {} was not successful",
+                    request_id.to_str()
+                ),
+            )
+        }
+    };
+    let subject_data = match api.get_subject(subject_id.clone()).await {
+        Ok(subject_data) => subject_data,
+        Err(e) => {
+            return fail_creation(
+                &sender,
+                TapleError::from_api_error(BUILD_OPERATION, Some(&subject_id.to_str()), e)
+                    .to_string(),
+            )
+        }
+    };
+    update_state(&sender, |state| {
+        state.progress.subject_id = Some(subject_id.to_str());
+        state.subject_data = Some(subject_data);
+    });
+
+    if let Some(payload) = initial_fact {
+        update_state(&sender, |state| {
+            state.progress.status = SubjectCreationStatus::SubmittingInitialFact
+        });
+        let fact_event = EventRequest::Fact(FactRequest {
+            subject_id: subject_id.clone(),
+            payload,
+        });
+        let fact_request_id = match sign_event(fact_event, &keys) {
+            Ok(signed_event) => api.external_request(signed_event).await.map_err(|e| {
+                TapleError::from_api_error(BUILD_OPERATION, Some(&subject_id.to_str()), e)
+            }),
+            Err(e) => Err(e),
         };
-        let derivator = KeyDerivator::Ed25519;
-
-        self.runtime.block_on(async {
-            match self.api.add_keys(derivator).await {
-                Ok(subject_key_identifier) => {
-                    let create_event = EventRequest::Create(StartRequest {
-                        governance_id: match DigestIdentifier::from_str(&governance_id) {
-                            Ok(gid) => gid,
-                            Err(e) => return Err(TapleError::ExecutionError(e.to_string())),
-                        },
-                        schema_id: schema_id,
-                        namespace: subject_namespace,
-                        name: subject_name,
-                        public_key: subject_key_identifier.clone(),
-                    });
-
-                    let event_signed = match Signature::new(&create_event, &self.keys) {
-                        Ok(signature) => Signed {
-                            content: create_event,
-                            signature,
-                        },
-                        Err(e) => return Err(TapleError::SignatureGenerationFailed(e.to_string())),
-                    };
-
-                    let request_id = self
-                        .api
-                        .external_request(event_signed)
-                        .await
-                        .map_err(|e| TapleError::ExecutionError(e.to_string()))?;
-
-                    //Once the subject is created is where it gets complicated:
-                    // 1. There is no direct way to know what has happened to the request.
-                    // 2. We need to wait for the subject's approval.
-                    // 3. We can't leave users hanging around waiting.
-
-                    //Solution:
-                    // 1. Return 2-upla with RequestID and SubjectBuilder.
-                    // The SubjecBuilder will check the status of the subject.
-
-                    let subject_api: Api = self.api.clone();
-                    let subject_keys: KeyPair = self.keys.clone();
-                    let subject_runtime: Arc<Runtime> = self.runtime.clone();
-                    Ok(Arc::new(create_subject(
-                        subject_api,
-                        subject_keys,
-                        subject_runtime,
-                        RwLock::new(None),
-                        Some(request_id),
-                    )))
-                }
-                Err(e) => Err(TapleError::ExecutionError(e.to_string())),
+        match fact_request_id {
+            Ok(fact_request_id) => update_state(&sender, |state| {
+                state.progress.initial_fact_request_id = Some(fact_request_id.to_str())
+            }),
+            // The subject exists, so it is still available through `get_subject`
+            Err(e) => return fail_creation(&sender, e.to_string()),
+        }
+    }
+
+    update_state(&sender, |state| {
+        state.progress.status = SubjectCreationStatus::Created
+    });
+}
+
+/// Waits until the creation ends, or fails with `RequestTimeout` once `timeout_ms` pass
+async fn wait_for_result(
+    mut receiver: watch::Receiver<CreationState>,
+    request_id: String,
+    timeout_ms: u64,
+) -> Result<(), TapleError> {
+    let wait = async {
+        loop {
+            let result = SubjectCreation::result(&receiver.borrow());
+            if let Some(result) = result {
+                return result;
             }
+            if receiver.changed().await.is_err() {
+                let result = SubjectCreation::result(&receiver.borrow());
+                return result.unwrap_or(Err(TapleError::InternalError));
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_millis(timeout_ms), wait)
+        .await
+        .map_err(|_| TapleError::RequestTimeout { request_id })?
+}
+
+/// Handle returned by `SubjectBuilder::build` to follow the creation of a subject
+#[derive(Clone)]
+pub struct SubjectCreation {
+    api: Api,
+    keys: KeyPair,
+    runtime: Handle,
+    notification_signal: broadcast::Sender<()>,
+    request_id: DigestIdentifier,
+    state: watch::Receiver<CreationState>,
+}
+
+impl SubjectCreation {
+    fn result(state: &CreationState) -> Option<Result<(), TapleError>> {
+        match state.progress.status {
+            SubjectCreationStatus::Created => Some(Ok(())),
+            SubjectCreationStatus::Failed => Some(Err(TapleError::ExecutionError {
+                operation: BUILD_OPERATION.to_owned(),
+                message: state.progress.error.clone().unwrap_or_default(),
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn get_request_id(&self) -> String {
+        self.request_id.to_str()
+    }
+
+    pub fn get_progress(&self) -> SubjectCreationProgress {
+        self.state.borrow().progress.clone()
+    }
+
+    /// Available as soon as the subject exists, even if the initial fact failed
+    pub fn get_subject(&self) -> Option<Arc<UserSubject>> {
+        let subject_data = self.state.borrow().subject_data.clone()?;
+        Some(Arc::new(create_subject(
+            self.api.clone(),
+            self.keys.clone(),
+            self.runtime.clone(),
+            RwLock::new(Some(subject_data)),
+            Some(self.request_id.clone()),
+            self.notification_signal.clone(),
+        )))
+    }
+
+    pub fn wait(&self, timeout_ms: u64) -> Result<Arc<UserSubject>, TapleError> {
+        self.runtime.block_on(self.wait_async(timeout_ms))
+    }
+}
+
+// Async exports, executed on the node runtime
+#[uniffi::export(async_runtime = "tokio")]
+impl SubjectCreation {
+    /// Waits until the subject is created and its initial fact submitted. The creation is
+    /// tracked for as long as the handle lives, so `timeout_ms` can be as long as needed
+    pub async fn wait_async(&self, timeout_ms: u64) -> Result<Arc<UserSubject>, TapleError> {
+        let creation = self.clone();
+        run_on_runtime(&self.runtime, async move {
+            wait_for_result(
+                creation.state.clone(),
+                creation.request_id.to_str(),
+                timeout_ms,
+            )
+            .await?;
+            creation.get_subject().ok_or(TapleError::InternalError)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creation_state() -> (watch::Sender<CreationState>, watch::Receiver<CreationState>) {
+        watch::channel(CreationState {
+            progress: SubjectCreationProgress {
+                request_id: "request".to_owned(),
+                status: SubjectCreationStatus::Requested,
+                subject_id: None,
+                initial_fact_request_id: None,
+                error: None,
+            },
+            subject_data: None,
         })
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_longer_than_a_minute() {
+        let (sender, receiver) = creation_state();
+        tokio::spawn(async move {
+            let request = tracked(&sender, false, tokio::time::sleep(Duration::from_secs(120)));
+            if request.await.is_some() {
+                update_state(&sender, |state| {
+                    state.progress.status = SubjectCreationStatus::Created
+                });
+            }
+        });
+        wait_for_result(receiver, "request".to_owned(), 180_000)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_times_out() {
+        let (_sender, receiver) = creation_state();
+        assert!(matches!(
+            wait_for_result(receiver, "request".to_owned(), 1000).await,
+            Err(TapleError::RequestTimeout { request_id }) if request_id == "request"
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_creation() {
+        let (sender, receiver) = creation_state();
+        fail_creation(&sender, "rejected".to_owned());
+        assert!(matches!(
+            wait_for_result(receiver, "request".to_owned(), 1000).await,
+            Err(TapleError::ExecutionError { message, .. }) if message == "rejected"
+        ));
+    }
+
+    #[tokio::test]
+    async fn tracking_stops_once_the_handles_are_dropped() {
+        let (sender, receiver) = creation_state();
+        drop(receiver);
+        assert!(tracked(&sender, false, std::future::pending::<()>())
+            .await
+            .is_none());
+        // The initial fact is still submitted
+        assert!(tracked(&sender, true, async {}).await.is_some());
+    }
 }
@@ -0,0 +1,501 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+use taple_core::{
+    request::EventRequest,
+    signature::{Signature, Signed},
+    ApprovalRequest, ApprovalResponse, Derivable, DigestIdentifier, Event,
+    ValidationProof as TapleValidationProof,
+};
+
+use crate::{
+    models::{
+        others::ValidationProofAndSignatures,
+        policy::{Policy, Quorum},
+        role::{Role, RoleEnum, SchemaEnum, Who},
+        verification::{ChainBreakReason, ChainVerificationReport, ValidationProofReport},
+    },
+    TapleError, TapleSignedApprovalRequest, TapleSignedApprovalResponse, TapleSignedEvent,
+    TapleSignedEventRequest,
+};
+
+fn invalid_signature(signature: &Signature, error: impl ToString) -> TapleError {
+    TapleError::InvalidSignature {
+        signer: signature.signer.to_str(),
+        message: error.to_string(),
+    }
+}
+
+/// Checks that the signature of the event request matches its content. Fails with
+/// `KeyIdentifierGenerationFailed` if the signer is not a valid `KeyIdentifier`.
+pub fn verify_event_request_signature(
+    event_request: TapleSignedEventRequest,
+) -> Result<(), TapleError> {
+    let event_request: Signed<EventRequest> = event_request.try_into()?;
+    event_request
+        .signature
+        .verify(&event_request.content)
+        .map_err(|e| invalid_signature(&event_request.signature, e))
+}
+
+pub fn verify_event_signature(event: TapleSignedEvent) -> Result<(), TapleError> {
+    let event: Signed<Event> = event.try_into()?;
+    event
+        .signature
+        .verify(&event.content)
+        .map_err(|e| invalid_signature(&event.signature, e))
+}
+
+pub fn verify_approval_request_signature(
+    approval_request: TapleSignedApprovalRequest,
+) -> Result<(), TapleError> {
+    let approval_request: Signed<ApprovalRequest> = approval_request.try_into()?;
+    approval_request
+        .signature
+        .verify(&approval_request.content)
+        .map_err(|e| invalid_signature(&approval_request.signature, e))
+}
+
+pub fn verify_approval_response_signature(
+    approval_response: TapleSignedApprovalResponse,
+) -> Result<(), TapleError> {
+    let approval_response: Signed<ApprovalResponse> = approval_response.try_into()?;
+    approval_response
+        .signature
+        .verify(&approval_response.content)
+        .map_err(|e| invalid_signature(&approval_response.signature, e))
+}
+
+struct ChainBreak {
+    sn: u64,
+    reason: ChainBreakReason,
+    detail: String,
+}
+
+/// Checks that `events` form a valid ledger for `subject_id`: consecutive sn starting at 0,
+/// an empty `hash_prev_event` in the genesis event, every other one pointing to the previous
+/// event and valid event and event request signatures. Evaluator and approver signatures cover the evaluation and approval responses,
+/// which are not part of the event, so they can't be checked here.
+pub(crate) fn verify_event_chain(
+    subject_id: &DigestIdentifier,
+    events: &[Signed<Event>],
+    expected_sn: Option<u64>,
+) -> ChainVerificationReport {
+    let mut events_checked = 0;
+    let mut result = Ok(());
+    let mut previous: Option<&Signed<Event>> = None;
+    for event in events {
+        result = verify_event(subject_id, event, previous);
+        if result.is_err() {
+            break;
+        }
+        events_checked += 1;
+        previous = Some(event);
+    }
+    if result.is_ok() {
+        if let Some(expected_sn) = expected_sn {
+            let next_sn = previous.map(|e| e.content.sn + 1).unwrap_or(0);
+            if next_sn <= expected_sn {
+                result = Err(ChainBreak {
+                    sn: next_sn,
+                    reason: ChainBreakReason::MissingEvents,
+                    detail: format!("Ledger ends before sn {}", expected_sn),
+                });
+            }
+        }
+    }
+    match result {
+        Ok(()) => ChainVerificationReport {
+            subject_id: subject_id.to_str(),
+            events_checked,
+            valid: true,
+            first_broken_sn: None,
+            reason: None,
+            detail: None,
+        },
+        Err(chain_break) => ChainVerificationReport {
+            subject_id: subject_id.to_str(),
+            events_checked,
+            valid: false,
+            first_broken_sn: Some(chain_break.sn),
+            reason: Some(chain_break.reason),
+            detail: Some(chain_break.detail),
+        },
+    }
+}
+
+fn verify_event(
+    subject_id: &DigestIdentifier,
+    event: &Signed<Event>,
+    previous: Option<&Signed<Event>>,
+) -> Result<(), ChainBreak> {
+    let sn = event.content.sn;
+    if &event.content.subject_id != subject_id {
+        return Err(ChainBreak {
+            sn,
+            reason: ChainBreakReason::SubjectMismatch,
+            detail: format!(
+                "Event belongs to subject {}",
+                event.content.subject_id.to_str()
+            ),
+        });
+    }
+    let expected_sn = previous.map(|p| p.content.sn + 1).unwrap_or(0);
+    if sn != expected_sn {
+        return Err(ChainBreak {
+            sn,
+            reason: ChainBreakReason::SequenceGap,
+            detail: format!("Expected sn {}", expected_sn),
+        });
+    }
+    verify_previous_hash(sn, &event.content.hash_prev_event, previous)?;
+    event
+        .signature
+        .verify(&event.content)
+        .map_err(|e| ChainBreak {
+            sn,
+            reason: ChainBreakReason::InvalidEventSignature,
+            detail: format!("Signer {}: {}", event.signature.signer.to_str(), e),
+        })?;
+    let event_request = &event.content.event_request;
+    event_request
+        .signature
+        .verify(&event_request.content)
+        .map_err(|e| ChainBreak {
+            sn,
+            reason: ChainBreakReason::InvalidRequestSignature,
+            detail: format!("Signer {}: {}", event_request.signature.signer.to_str(), e),
+        })?;
+    Ok(())
+}
+
+/// The genesis event must have an empty `hash_prev_event`; any other event must point to
+/// the hash of `previous`
+fn verify_previous_hash(
+    sn: u64,
+    hash_prev_event: &DigestIdentifier,
+    previous: Option<&Signed<Event>>,
+) -> Result<(), ChainBreak> {
+    let Some(previous) = previous else {
+        if !hash_prev_event.to_str().is_empty() {
+            return Err(ChainBreak {
+                sn,
+                reason: ChainBreakReason::PreviousHashMismatch,
+                detail: format!(
+                    "Genesis event has hash_prev_event {}",
+                    hash_prev_event.to_str()
+                ),
+            });
+        }
+        return Ok(());
+    };
+    let previous_hash =
+        DigestIdentifier::from_serializable_borsh(&previous.content).map_err(|e| ChainBreak {
+            sn: previous.content.sn,
+            reason: ChainBreakReason::HashComputationFailed,
+            detail: e.to_string(),
+        })?;
+    if hash_prev_event != &previous_hash {
+        return Err(ChainBreak {
+            sn,
+            reason: ChainBreakReason::PreviousHashMismatch,
+            detail: format!(
+                "hash_prev_event is {} but previous event hash is {}",
+                hash_prev_event.to_str(),
+                previous_hash.to_str()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Quorum of `validators_count` validators. A fixed quorum may be unreachable if the
+/// governance defines fewer validators than it requires.
+fn quorum_required(quorum: &Quorum, validators_count: u64) -> u64 {
+    match quorum {
+        Quorum::MAJORITY => validators_count / 2 + 1,
+        Quorum::FIXED { value } => *value,
+        Quorum::PERCENTAGE { value } => (validators_count as f64 * value).ceil() as u64,
+    }
+}
+
+/// Checks the signatures of a validation proof against the validators and the `validate`
+/// quorum defined by the governance `properties` at `governance_version`. Only the validators
+/// the governance lists are counted: signers admitted by an `ALL` or `NOT_MEMBERS` role can't be
+/// resolved offline, so they are reported in `non_validator_signers`.
+pub(crate) fn verify_validation_proof(
+    proof_and_signatures: ValidationProofAndSignatures,
+    properties: &Value,
+    governance_version: u64,
+) -> Result<ValidationProofReport, TapleError> {
+    let proof: TapleValidationProof = proof_and_signatures.validation_proof.try_into()?;
+    let validators = get_validators(properties, &proof.schema_id, &proof.namespace)?;
+    let quorum = get_validation_quorum(properties, &proof.schema_id)?;
+
+    let checked_signatures = proof_and_signatures
+        .signatures
+        .into_iter()
+        .map(|signature| {
+            let signer = signature.signer.clone();
+            let verified = TryInto::<Signature>::try_into(signature)
+                .map(|signature| signature.verify(&proof).is_ok())
+                .unwrap_or(false);
+            (signer, verified)
+        });
+    let signers = classify_signers(checked_signatures, &validators);
+
+    let quorum_required = quorum_required(&quorum, validators.len() as u64);
+    let governance_version_matches = proof.governance_version == governance_version;
+    Ok(ValidationProofReport {
+        // Invalid or extra signatures can be appended by anyone, so they don't void the proof
+        valid: governance_version_matches && signers.accepted.len() as u64 >= quorum_required,
+        governance_version_matches,
+        quorum_required,
+        accepted_signers: signers.accepted,
+        invalid_signatures: signers.invalid,
+        non_validator_signers: signers.non_validators,
+    })
+}
+
+#[derive(Debug, Default)]
+struct Signers {
+    /// Distinct validators with a valid signature
+    accepted: Vec<String>,
+    invalid: Vec<String>,
+    non_validators: Vec<String>,
+}
+
+/// Sorts the signers of a proof by the result of checking their signature
+fn classify_signers(
+    checked_signatures: impl IntoIterator<Item = (String, bool)>,
+    validators: &HashSet<String>,
+) -> Signers {
+    let mut signers = Signers::default();
+    for (signer, verified) in checked_signatures {
+        if !verified {
+            signers.invalid.push(signer);
+        } else if !validators.contains(&signer) {
+            signers.non_validators.push(signer);
+        } else if !signers.accepted.contains(&signer) {
+            signers.accepted.push(signer);
+        }
+    }
+    signers
+}
+
+fn get_validators(
+    properties: &Value,
+    schema_id: &str,
+    namespace: &str,
+) -> Result<HashSet<String>, TapleError> {
+    let members = properties
+        .get("members")
+        .and_then(|m| m.as_array())
+        .ok_or(TapleError::IncorrectGovernanceProperties)?;
+    let mut member_ids = HashSet::new();
+    let mut member_names = Vec::new();
+    for member in members {
+        let id = member
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or(TapleError::IncorrectGovernanceProperties)?;
+        let name = member
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or(TapleError::IncorrectGovernanceProperties)?;
+        member_ids.insert(id.to_owned());
+        member_names.push((name.to_owned(), id.to_owned()));
+    }
+    let roles: Vec<Role> = serde_json::from_value(
+        properties
+            .get("roles")
+            .ok_or(TapleError::IncorrectGovernanceProperties)?
+            .to_owned(),
+    )
+    .map_err(|_| TapleError::DeserializationError)?;
+
+    let mut validators = HashSet::new();
+    for role in roles {
+        if !matches!(role.role, RoleEnum::VALIDATOR) {
+            continue;
+        }
+        let schema_matches = match &role.schema {
+            SchemaEnum::ID { ID: id } => id == schema_id,
+            SchemaEnum::NOT_GOVERNANCE => schema_id != "governance",
+            SchemaEnum::ALL => true,
+        };
+        let namespace_matches = role.namespace.is_empty()
+            || namespace == role.namespace
+            || namespace.starts_with(&format!("{}.", role.namespace));
+        if !schema_matches || !namespace_matches {
+            continue;
+        }
+        match role.who {
+            Who::ID { ID: id } => {
+                validators.insert(id);
+            }
+            Who::NAME { NAME: role_name } => {
+                for (name, id) in member_names.iter() {
+                    if name == &role_name {
+                        validators.insert(id.clone());
+                    }
+                }
+            }
+            // Non members can't be listed, so `ALL` only resolves to the members
+            Who::MEMBERS | Who::ALL => validators.extend(member_ids.iter().cloned()),
+            Who::NOT_MEMBERS => {}
+        }
+    }
+    Ok(validators)
+}
+
+fn get_validation_quorum(properties: &Value, schema_id: &str) -> Result<Quorum, TapleError> {
+    let policies: Vec<Policy> = serde_json::from_value(
+        properties
+            .get("policies")
+            .ok_or(TapleError::IncorrectGovernanceProperties)?
+            .to_owned(),
+    )
+    .map_err(|_| TapleError::DeserializationError)?;
+    policies
+        .into_iter()
+        .find(|policy| policy.id == schema_id)
+        .map(|policy| policy.validate.quorum)
+        .ok_or(TapleError::NotFound {
+            operation: "verify_validation_proof".to_owned(),
+            id: Some(schema_id.to_owned()),
+            message: "No policy defined for the schema".to_owned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn governance(roles: Value) -> Value {
+        json!({
+            "members": [
+                {"id": "alice_key", "name": "alice"},
+                {"id": "bob_key", "name": "bob"},
+                {"id": "carol_key", "name": "carol"},
+            ],
+            "roles": roles,
+            "policies": [{
+                "id": "schema",
+                "approve": {"quorum": "MAJORITY"},
+                "evaluate": {"quorum": "MAJORITY"},
+                "validate": {"quorum": {"PERCENTAGE": 0.5}},
+            }],
+        })
+    }
+
+    fn validator(who: Value, namespace: &str, schema: Value) -> Value {
+        json!({"who": who, "namespace": namespace, "role": "VALIDATOR", "schema": schema})
+    }
+
+    fn sorted(validators: HashSet<String>) -> Vec<String> {
+        let mut validators: Vec<String> = validators.into_iter().collect();
+        validators.sort();
+        validators
+    }
+
+    #[test]
+    fn genesis_event_must_not_point_to_a_previous_event() {
+        assert!(verify_previous_hash(0, &DigestIdentifier::default(), None).is_ok());
+        let forged = DigestIdentifier::from_serializable_borsh(&"forged".to_owned()).unwrap();
+        let chain_break = verify_previous_hash(0, &forged, None).unwrap_err();
+        assert_eq!(chain_break.sn, 0);
+        assert!(matches!(
+            chain_break.reason,
+            ChainBreakReason::PreviousHashMismatch
+        ));
+    }
+
+    #[test]
+    fn quorum_depends_only_on_validators_count() {
+        assert_eq!(quorum_required(&Quorum::MAJORITY, 4), 3);
+        assert_eq!(quorum_required(&Quorum::MAJORITY, 3), 2);
+        assert_eq!(quorum_required(&Quorum::MAJORITY, 0), 1);
+        assert_eq!(quorum_required(&Quorum::FIXED { value: 2 }, 5), 2);
+        assert_eq!(quorum_required(&Quorum::PERCENTAGE { value: 0.5 }, 3), 2);
+        assert_eq!(quorum_required(&Quorum::PERCENTAGE { value: 1.0 }, 3), 3);
+    }
+
+    #[test]
+    fn validators_resolved_by_id_and_name() {
+        let properties = governance(json!([
+            validator(json!({"ID": "dave_key"}), "", json!("ALL")),
+            validator(json!({"NAME": "bob"}), "", json!({"ID": "schema"})),
+            validator(json!({"NAME": "carol"}), "", json!({"ID": "other"})),
+            validator(json!({"NAME": "alice"}), "other", json!("ALL")),
+        ]));
+        let validators = get_validators(&properties, "schema", "").unwrap();
+        assert_eq!(sorted(validators), vec!["bob_key", "dave_key"]);
+    }
+
+    #[test]
+    fn namespace_roles_cover_child_namespaces() {
+        let properties = governance(json!([validator(
+            json!({"NAME": "alice"}),
+            "org",
+            json!("NOT_GOVERNANCE")
+        )]));
+        let validators = get_validators(&properties, "schema", "org.unit").unwrap();
+        assert_eq!(sorted(validators), vec!["alice_key"]);
+        let validators = get_validators(&properties, "schema", "organization").unwrap();
+        assert!(validators.is_empty());
+        let validators = get_validators(&properties, "governance", "org").unwrap();
+        assert!(validators.is_empty());
+    }
+
+    #[test]
+    fn open_roles_only_count_members() {
+        let properties = governance(json!([
+            validator(json!("ALL"), "", json!("ALL")),
+            validator(json!("NOT_MEMBERS"), "", json!("ALL")),
+        ]));
+        let validators = get_validators(&properties, "schema", "").unwrap();
+        assert_eq!(
+            sorted(validators),
+            vec!["alice_key", "bob_key", "carol_key"]
+        );
+
+        let properties = governance(json!([validator(json!("NOT_MEMBERS"), "", json!("ALL"))]));
+        let validators = get_validators(&properties, "schema", "").unwrap();
+        assert!(validators.is_empty());
+    }
+
+    #[test]
+    fn junk_and_duplicate_signatures_are_reported_apart() {
+        let validators: HashSet<String> = ["alice_key", "bob_key"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let signers = classify_signers(
+            [
+                ("alice_key".to_owned(), true),
+                ("alice_key".to_owned(), true),
+                ("bob_key".to_owned(), false),
+                ("mallory_key".to_owned(), true),
+                ("bob_key".to_owned(), true),
+            ],
+            &validators,
+        );
+        assert_eq!(signers.accepted, vec!["alice_key", "bob_key"]);
+        assert_eq!(signers.invalid, vec!["bob_key"]);
+        assert_eq!(signers.non_validators, vec!["mallory_key"]);
+    }
+
+    #[test]
+    fn validation_quorum_of_the_schema_policy() {
+        let properties = governance(json!([]));
+        let quorum = get_validation_quorum(&properties, "schema").unwrap();
+        assert!(matches!(quorum, Quorum::PERCENTAGE { value } if value == 0.5));
+        assert!(matches!(
+            get_validation_quorum(&properties, "other"),
+            Err(TapleError::NotFound { .. })
+        ));
+    }
+}